@@ -9,50 +9,9 @@ pub fn load_assets(state: &mut GameState, context: &mut Context_) {
 }
 
 pub fn load_game_data() -> GameData {
-    let mut game_data = hike_data::GameData::new();
-
-    let fixtures = game_data.add_entities_from_str(
-        include_str!("../../../assets/data/fixtures.yaml").to_string()
-    );
-    // let traps = game_data.add_entities_from_str(
-    //     include_str!("../../../assets/data/traps.yaml").to_string()
-    // );
-    let npcs = game_data.add_entities_from_str(
-        include_str!("../../../assets/data/npcs.yaml").to_string()
-    );
-    let _ = game_data.add_entities_from_str(
-        include_str!("../../../assets/data/player.yaml").to_string()
-    );
-    let _ = game_data.add_entities_from_str(
-        include_str!("../../../assets/data/board_elements.yaml").to_string()
-    );
-    let mut items = game_data.add_entities_from_str(
-        include_str!("../../../assets/data/items.yaml").to_string()
-    );
-    let discoverables = game_data.add_entities_from_str(
-        include_str!("../../../assets/data/discoverables.yaml").to_string()
-    );
-    let weapons = game_data.add_entities_from_str(
-        include_str!("../../../assets/data/weapons.yaml").to_string()
-    );
-    let _ = game_data.add_entities_from_str(
-        include_str!("../../../assets/data/info.yaml").to_string()
-    );
-
-    items.extend(discoverables.clone());
-
-    game_data.npcs = npcs;
-    game_data.discoverables = discoverables;
-    game_data.assign_discoverables();
-
-    game_data.items = items;
-    game_data.fixtures = fixtures;
-    game_data.weapons = weapons;
-    // game_data.traps = traps;
-    
-    game_data.add_level_data_from_str(
-        include_str!("../../../assets/data/levels.yaml").to_string()
-    );
+    let game_data = GameData::load_embedded();
+    // affixes are made of the game components
+    hike_game::validate_affixes(&game_data).expect("Invalid affix!");
     game_data
 }
 
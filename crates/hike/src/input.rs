@@ -49,6 +49,7 @@ pub fn get_input_state(
 
     let action_right = key_state(context, KeyCode::KeyE);
     let action_left = key_state(context, KeyCode::KeyQ);
+    let drop = key_state(context, KeyCode::KeyG);
 
     let mut direction = if !settings.dpad {
         handle_touches(context, touch_state, settings)
@@ -85,6 +86,8 @@ pub fn get_input_state(
         key_state(context, KeyCode::KeyX),
         key_state(context, KeyCode::KeyC),
         key_state(context, KeyCode::KeyV),
+        key_state(context, KeyCode::KeyB),
+        key_state(context, KeyCode::KeyN),
     ];
 
     let m = context.input.get_mouse_physical_position();
@@ -102,7 +105,8 @@ pub fn get_input_state(
         action_left,
         action_right,
         digits,
-        item_action
+        item_action,
+        drop
     }
 }
 
@@ -17,7 +17,6 @@ use wasm_bindgen::prelude::*;
 
 mod assets;
 mod input;
-mod serialize;
 
 pub type Context_ = Context<WgpuContext>;
 const SETTINGS_NAME: &str = "monk_settings";
@@ -122,7 +121,7 @@ impl Game<WgpuContext> for GameState {
                 for ev in self.ev_game.read().iter().flatten() {
                     match *ev {
                         hike_game::GameEvent::TurnEnd => {
-                            if let Ok(save) = self.world.serialize() {
+                            if let Ok(save) = hike_game::save(&self.world) {
                                 let _ = persist::store_raw(SAVE_NAME, &save, context.os_path.as_deref());
                             }
                         },
@@ -143,15 +142,22 @@ impl Game<WgpuContext> for GameState {
                 }
             },
             GamePhase::GameStart => {
+                // from the game rng, so that a seeded run replays the same appearances
+                self.data.assign_discoverables(&mut hike_game::get_rng());
                 hike_game::init(&mut self.world, &mut self.events.game_events, self.data.clone());
                 self.phase = GamePhase::Game;
             },
             GamePhase::GameResume => {
                 self.can_resume = false;
                 if let Ok(saved_state) = persist::load_raw(SAVE_NAME, context.os_path.as_deref()) {
-                    hike_game::restore(&mut self.world, self.data.clone(), saved_state);
-                    self.graphics_state.restore(&self.world);
-                    self.phase = GamePhase::Game;
+                    if hike_game::restore(&mut self.world, self.data.clone(), saved_state).is_ok() {
+                        self.graphics_state.restore(&self.world);
+                        self.phase = GamePhase::Game;
+                    } else {
+                        // an outdated or broken save, start over
+                        let _ = persist::remove(SAVE_NAME, context.os_path.as_deref());
+                        self.phase = GamePhase::GameRestart;
+                    }
                 } else {
                     self.phase = GamePhase::Crash
                 }
@@ -234,7 +240,7 @@ fn game_state() -> GameState {
 
 fn get_initial_elements() -> (World, Events, hike_graphics::GraphicsState, hike_audio::AudioContext) {
     let mut world = World::new();
-    serialize::register_serialized(&mut world);
+    hike_game::register_serialized(&mut world);
     let mut events = Events::new();
     let mut graphics_state = hike_graphics::GraphicsState::new(
         &mut world,
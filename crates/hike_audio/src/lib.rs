@@ -16,10 +16,10 @@ use hike_game::{GameEvent, get_player_entity};
 pub fn handle_game_audio(context: &mut AudioContext, world: &World) {
     for ev in context.ev_game.read().iter().flatten() {
         match ev {
-            GameEvent::PickItem => {
+            GameEvent::PickItem | GameEvent::Open(_) => {
                 context.play("pick");
             },
-            GameEvent::Spawn => {
+            GameEvent::Spawn | GameEvent::BossPhase(_) => {
                 context.play("spawn");
             }
             GameEvent::UseCollectable => {
@@ -36,10 +36,10 @@ pub fn handle_game_audio(context: &mut AudioContext, world: &World) {
             GameEvent::Attack(_, _) | GameEvent::HitProjectile(_) => {
                 context.play("hit");
             },
-            GameEvent::Upgrade => {
+            GameEvent::Upgrade | GameEvent::Enchant(_, _) | GameEvent::Sacrifice(_, _) => {
                 context.play("upgrade");
             },
-            GameEvent::Ascend => {
+            GameEvent::Ascend | GameEvent::Descend => {
                 context.play("ascend");
             },
             GameEvent::Win => {
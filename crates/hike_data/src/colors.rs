@@ -1,3 +1,4 @@
+use rand::prelude::*;
 use rogalik::engine::Color;
 
 pub const COLORS: [(&str, Color); 8] = [
@@ -9,4 +10,26 @@ pub const COLORS: [(&str, Color); 8] = [
     ("Yellow", Color(255, 191, 102, 255)),
     ("Pink", Color(255, 102, 145, 255)),
     ("Blue", Color(89, 116, 166, 255)),
-];
\ No newline at end of file
+];
+// used to extend the pool once the base colors are exhausted
+const SHADES: [&str; 4] = ["Murky", "Fizzy", "Cloudy", "Glowing"];
+
+pub fn get_color_names<R: Rng>(count: usize, rng: &mut R) -> Vec<(String, Color)> {
+    let mut output = Vec::new();
+    let mut tier = 0;
+    while output.len() < count {
+        let mut pool = COLORS.to_vec();
+        pool.shuffle(rng);
+        output.extend(pool.iter().map(|(name, color)| {
+            let name = match tier {
+                0 => name.to_string(),
+                t if t <= SHADES.len() => format!("{} {}", SHADES[t - 1], name),
+                t => format!("{} {} {}", SHADES[(t - 1) % SHADES.len()], name, t)
+            };
+            (name, *color)
+        }));
+        tier += 1;
+    }
+    output.truncate(count);
+    output
+}
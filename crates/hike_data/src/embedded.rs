@@ -0,0 +1,91 @@
+use rand::thread_rng;
+
+use crate::GameData;
+
+impl GameData {
+    pub fn load_embedded() -> Self {
+        // the game data compiled into the binary
+        let mut game_data = GameData::new();
+
+        let fixtures = game_data.add_entities_from_str(
+            include_str!("../../../assets/data/fixtures.yaml").to_string()
+        );
+        let containers = game_data.add_entities_from_str(
+            include_str!("../../../assets/data/containers.yaml").to_string()
+        );
+        let terrain = game_data.add_entities_from_str(
+            include_str!("../../../assets/data/terrain.yaml").to_string()
+        );
+        // hazards are spawned only by other entities
+        let _ = game_data.add_entities_from_str(
+            include_str!("../../../assets/data/hazards.yaml").to_string()
+        );
+        let traps = game_data.add_entities_from_str(
+            include_str!("../../../assets/data/traps.yaml").to_string()
+        );
+        let npcs = game_data.add_entities_from_str(
+            include_str!("../../../assets/data/npcs.yaml").to_string()
+        );
+        // bosses are not a part of the random npc pool
+        let _ = game_data.add_entities_from_str(
+            include_str!("../../../assets/data/bosses.yaml").to_string()
+        );
+        let _ = game_data.add_entities_from_str(
+            include_str!("../../../assets/data/player.yaml").to_string()
+        );
+        let _ = game_data.add_entities_from_str(
+            include_str!("../../../assets/data/board_elements.yaml").to_string()
+        );
+        let mut items = game_data.add_entities_from_str(
+            include_str!("../../../assets/data/items.yaml").to_string()
+        );
+        let mut discoverables = game_data.add_entities_from_str(
+            include_str!("../../../assets/data/discoverables.yaml").to_string()
+        );
+        let scrolls = game_data.add_entities_from_str(
+            include_str!("../../../assets/data/scrolls.yaml").to_string()
+        );
+        let equipment = game_data.add_entities_from_str(
+            include_str!("../../../assets/data/equipment.yaml").to_string()
+        );
+        let weapons = game_data.add_entities_from_str(
+            include_str!("../../../assets/data/weapons.yaml").to_string()
+        );
+        let _ = game_data.add_entities_from_str(
+            include_str!("../../../assets/data/info.yaml").to_string()
+        );
+
+        discoverables.extend(scrolls.clone());
+        items.extend(discoverables.clone());
+        items.extend(equipment);
+
+        game_data.npcs = npcs;
+        game_data.discoverables = discoverables;
+        game_data.scrolls = scrolls;
+        game_data.assign_discoverables(&mut thread_rng());
+
+        game_data.items = items;
+        game_data.fixtures = fixtures;
+        game_data.containers = containers;
+        game_data.terrain = terrain;
+        game_data.weapons = weapons;
+        game_data.traps = traps;
+
+        game_data.add_affixes_from_str(
+            include_str!("../../../assets/data/affixes.yaml").to_string()
+        );
+        game_data.add_loot_tables_from_str(
+            include_str!("../../../assets/data/loot.yaml").to_string()
+        );
+        game_data.validate_loot_tables().expect("Invalid loot table reference!");
+        game_data.add_prefabs_from_str(
+            include_str!("../../../assets/data/prefabs.yaml").to_string()
+        );
+        game_data.validate_prefabs().expect("Invalid prefab!");
+        game_data.add_level_data_from_str(
+            include_str!("../../../assets/data/levels.yaml").to_string()
+        );
+        game_data.validate_levels().expect("Invalid level data!");
+        game_data
+    }
+}
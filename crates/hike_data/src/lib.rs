@@ -1,13 +1,17 @@
 use rand::prelude::*;
 use serde::{Deserialize, Deserializer};
 use serde_yaml;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
 
 use rogalik::engine::Color;
 
 pub mod colors;
+mod embedded;
+mod loot;
+mod names;
 mod settings;
 
+pub use loot::{LootEntry, LootTable};
 pub use settings::Settings;
 
 #[derive(Clone, Default)]
@@ -15,13 +19,24 @@ pub struct GameData {
     // all entity data, by name
     pub entities: HashMap<String, EntityData>,
     pub levels: HashMap<u32, LevelData>,
+    // ordered, as the branches are drawn by the seeded generation
+    pub branches: BTreeMap<String, BranchData>,
+    // all discoverables, including the scrolls
     pub discoverables: Vec<String>,
+    pub scrolls: Vec<String>,
     pub items: Vec<String>,
     pub npcs: Vec<String>,
     pub fixtures: Vec<String>,
-    // pub traps: Vec<String>,
+    pub containers: Vec<String>,
+    pub terrain: Vec<String>,
+    pub traps: Vec<String>,
     pub weapons: Vec<String>,
-    pub discoverable_colors: HashMap<String,  (&'static str, Color)>
+    pub appearances: HashMap<String, Appearance>,
+    // ordered, as the affixes are drawn by the seeded spawning
+    pub affixes: BTreeMap<String, AffixData>,
+    pub loot_tables: HashMap<String, LootTable>,
+    // ordered, as the prefabs are drawn by the seeded generation
+    pub prefabs: BTreeMap<String, PrefabData>
 }
 impl GameData {
     pub fn new() -> Self {
@@ -42,23 +57,136 @@ impl GameData {
         }
         inserted_names
     }
+    pub fn add_affixes_from_str(&mut self, s: String) {
+        self.affixes = serde_yaml::from_str(&s).expect("Invalid affix data!");
+    }
+    pub fn add_loot_tables_from_str(&mut self, s: String) {
+        self.loot_tables = serde_yaml::from_str(&s).expect("Invalid loot table data!");
+    }
+    pub fn roll_loot(&self, table: &str, level: u32, rng: &mut impl Rng) -> Vec<String> {
+        let Some(loot_table) = self.loot_tables.get(table) else { return Vec::new() };
+        loot_table.roll(&self.loot_tables, level, rng)
+    }
+    pub fn validate_loot_tables(&self) -> Result<(), String> {
+        // every table entry should point to an existing entity or table
+        for (name, table) in self.loot_tables.iter() {
+            for entry in table.entries.iter() {
+                if let Some(item) = &entry.item {
+                    if !self.entities.contains_key(item) {
+                        return Err(format!("Unknown entity: {} in loot table: {}", item, name));
+                    }
+                }
+                if let Some(nested) = &entry.table {
+                    if !self.loot_tables.contains_key(nested) {
+                        return Err(format!("Unknown table: {} in loot table: {}", nested, name));
+                    }
+                }
+            }
+            if loot::has_table_cycle(&self.loot_tables, name, &mut Vec::new()) {
+                return Err(format!("Cyclic loot table: {}", name));
+            }
+        }
+        // every Loot component should reference an existing table
+        for (name, entity) in self.entities.iter() {
+            let Some(table) = entity.components.get("Loot").and_then(|l| l.get("table")) else { continue };
+            let Some(table) = table.as_str() else {
+                return Err(format!("Incorrect loot table name at: {}", name));
+            };
+            if !self.loot_tables.contains_key(table) {
+                return Err(format!("Unknown table: {} at: {}", table, name));
+            }
+        }
+        Ok(())
+    }
+    pub fn add_prefabs_from_str(&mut self, s: String) {
+        self.prefabs = serde_yaml::from_str(&s).expect("Invalid prefab data!");
+    }
+    pub fn validate_prefabs(&self) -> Result<(), String> {
+        // every legend entry should point to an existing entity
+        for (name, prefab) in self.prefabs.iter() {
+            if prefab.map.is_empty() {
+                return Err(format!("Empty map in prefab: {}", name));
+            }
+            for (c, entity) in prefab.legend.iter() {
+                if !self.entities.contains_key(entity) {
+                    return Err(format!("Unknown entity: {} in prefab: {}", entity, name));
+                }
+                if *c == PREFAB_FLOOR {
+                    return Err(format!("Reserved character: {} in prefab: {}", c, name));
+                }
+            }
+            for c in prefab.map.iter().flat_map(|r| r.chars()) {
+                if c != PREFAB_FLOOR && !prefab.legend.contains_key(&c) {
+                    return Err(format!("Unknown character: {} in prefab: {}", c, name));
+                }
+            }
+        }
+        Ok(())
+    }
     pub fn add_level_data_from_str(&mut self, s: String) {
-        self.levels = serde_yaml::from_str(&s).expect("Invalid level data!");
+        let data: LevelFile = serde_yaml::from_str(&s).expect("Invalid level data!");
+        self.levels = data.levels;
+        self.branches = data.branches;
+    }
+    pub fn validate_levels(&self) -> Result<(), String> {
+        // bosses and the required pieces should point to existing entities
+        for (level, data) in self.levels.iter() {
+            let names = data.required_items.iter()
+                .chain(data.required_npcs.iter())
+                .chain(data.required_fixtures.iter())
+                .chain(data.boss.iter());
+            for name in names {
+                if !self.entities.contains_key(name) {
+                    return Err(format!("Unknown entity: {} at level: {}", name, level));
+                }
+            }
+        }
+        // as well as the branch pools and palettes
+        for (branch, data) in self.branches.iter() {
+            let names = data.items.iter()
+                .chain(data.npcs.iter())
+                .chain(data.weapons.iter())
+                .chain(data.palette.keys());
+            for name in names {
+                if !self.entities.contains_key(name) {
+                    return Err(format!("Unknown entity: {} in branch: {}", name, branch));
+                }
+            }
+        }
+        Ok(())
     }
-    pub fn assign_discoverables(&mut self) {
-        let mut rng = thread_rng();
-        self.discoverable_colors = HashMap::new();
-        let mut pool = colors::COLORS.to_vec();
-        if pool.len() < self.discoverables.len() { panic!("Not enough colors in the pool!")};
+    pub fn assign_discoverables<R: Rng>(&mut self, rng: &mut R) {
+        // takes the game's rng, so that a seeded run replays the same appearances
+        self.appearances = HashMap::new();
+        let mut colors = colors::get_color_names(self.discoverables.len(), rng).into_iter();
+        let mut titles = HashSet::new();
 
         for name in self.discoverables.iter() {
-            let i = rng.gen_range(0..pool.len());
-            let color = pool.remove(i);
-            self.discoverable_colors.insert(name.to_string(), color);
+            let appearance = if self.scrolls.contains(name) {
+                let title = loop {
+                    let title = names::get_gibberish(rng);
+                    if titles.insert(title.clone()) { break title }
+                };
+                Appearance {
+                    name: format!("Scroll '{}'", title),
+                    color: colors::COLORS.choose(rng).unwrap().1
+                }
+            } else {
+                let (color_name, color) = colors.next().unwrap();
+                Appearance { name: format!("{} potion", color_name), color }
+            };
+            self.appearances.insert(name.to_string(), appearance);
         };
     }
 }
 
+#[derive(Clone)]
+pub struct Appearance {
+    // shown until the item is identified
+    pub name: String,
+    pub color: Color
+}
+
 #[derive(Clone, Deserialize)]
 pub struct EntityData {
     pub sprite: SpriteData,
@@ -69,7 +197,31 @@ pub struct EntityData {
     pub max_level: u32,
     pub spawn_chance: Option<f32>,
     #[serde(default)]
-    pub score: i32
+    pub score: i32,
+    // merchant price - items without it are not sold
+    pub price: Option<u32>
+}
+
+#[derive(Clone, Deserialize)]
+pub struct AffixData {
+    pub kind: AffixKind,
+    #[serde(default)]
+    pub min_level: u32,
+    pub spawn_chance: Option<f32>,
+    // attacks added to the weapon's offensive
+    #[serde(default)]
+    pub attacks: serde_yaml::Value,
+    #[serde(default)]
+    pub durability: u32,
+    // extra components inserted to the weapon
+    #[serde(default)]
+    pub components: serde_yaml::Value
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+pub enum AffixKind {
+    Prefix,
+    Suffix
 }
 
 #[derive(Clone, Deserialize)]
@@ -89,7 +241,93 @@ pub struct LevelData {
     #[serde(default)]
     pub required_npcs: Vec<String>,
     #[serde(default)]
-    pub required_fixtures: Vec<String>
+    pub required_fixtures: Vec<String>,
+    // boss entity guarding the floor exit
+    pub boss: Option<String>,
+    // overrides the default trap density
+    pub traps: Option<u32>,
+    // overrides the random dark floor roll
+    pub dark: Option<bool>,
+    #[serde(default)]
+    pub layout: LayoutKind
+}
+
+// empty tile of a prefab map
+pub const PREFAB_FLOOR: char = '.';
+
+#[derive(Clone, Deserialize)]
+pub struct PrefabData {
+    // ascii rows, all the characters but the floor are keys of the legend
+    pub map: Vec<String>,
+    pub legend: HashMap<char, String>,
+    #[serde(default)]
+    pub min_level: u32,
+    #[serde(default)]
+    pub max_level: u32,
+    pub spawn_chance: Option<f32>
+}
+impl PrefabData {
+    pub fn dim(&self) -> (u32, u32) {
+        let w = self.map.iter().map(|r| r.chars().count()).max().unwrap_or(0);
+        (w as u32, self.map.len() as u32)
+    }
+}
+
+#[derive(Deserialize)]
+struct LevelFile {
+    #[serde(default)]
+    levels: HashMap<u32, LevelData>,
+    #[serde(default)]
+    branches: BTreeMap<String, BranchData>
+}
+
+#[derive(Clone, Deserialize)]
+pub struct BranchData {
+    // floors spanned by the branch - the entrance is placed on the floor below
+    pub min_level: u32,
+    pub max_level: u32,
+    // chance of the entrance being placed
+    #[serde(default = "default_branch_chance")]
+    pub chance: f64,
+    // replace the default spawn pools, when not empty
+    #[serde(default)]
+    pub items: Vec<String>,
+    #[serde(default)]
+    pub npcs: Vec<String>,
+    #[serde(default)]
+    pub weapons: Vec<String>,
+    #[serde(default)]
+    pub extra_weapons: u32,
+    // added to the floor's npc score
+    #[serde(default)]
+    pub difficulty: i32,
+    // sprite color overrides, by the entity name
+    #[serde(default)]
+    #[serde(deserialize_with="deserialize_palette")]
+    pub palette: HashMap<String, Color>
+}
+
+fn default_branch_chance() -> f64 { 1. }
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq)]
+pub enum LayoutKind {
+    #[default]
+    Bsp,
+    Arena,
+    Caves,
+    Maze,
+    // a single hall
+    Open,
+    // corridors around a central room
+    Ring
+}
+
+fn deserialize_palette<'de, D>(d: D) -> Result<HashMap<String, Color>, D::Error>
+where D: Deserializer<'de> {
+    let values = HashMap::<String, serde_yaml::Value>::deserialize(d)?;
+    values.into_iter()
+        .map(|(k, v)| Ok((k, deserialize_color(v).map_err(serde::de::Error::custom)?)))
+        .collect()
 }
 
 fn deserialize_color<'de, D>(d: D) -> Result<Color, D::Error>
@@ -0,0 +1,234 @@
+use rand::prelude::*;
+use serde::{Deserialize, Deserializer};
+use std::collections::HashMap;
+
+// guards against cyclic table references (rejected by the validation as well)
+const MAX_TABLE_DEPTH: u32 = 8;
+
+#[derive(Clone, Deserialize)]
+pub struct LootTable {
+    // how many times the entries are drawn
+    #[serde(default = "default_count")]
+    #[serde(deserialize_with="deserialize_count")]
+    pub rolls: (u32, u32),
+    pub entries: Vec<LootEntry>
+}
+impl LootTable {
+    pub fn roll(
+        &self,
+        tables: &HashMap<String, LootTable>,
+        level: u32,
+        rng: &mut impl Rng
+    ) -> Vec<String> {
+        self.roll_nested(tables, level, rng, 0)
+    }
+    fn roll_nested(
+        &self,
+        tables: &HashMap<String, LootTable>,
+        level: u32,
+        rng: &mut impl Rng,
+        depth: u32
+    ) -> Vec<String> {
+        let mut output = Vec::new();
+        if depth >= MAX_TABLE_DEPTH { return output }
+
+        let pool = self.entries.iter()
+            .filter(|e| e.min_level <= level && (e.max_level == 0 || e.max_level >= level))
+            .collect::<Vec<_>>();
+
+        for _ in 0..rng.gen_range(self.rolls.0..=self.rolls.1) {
+            let Ok(entry) = pool.choose_weighted(rng, |e| e.weight) else { break };
+            for _ in 0..rng.gen_range(entry.count.0..=entry.count.1) {
+                if let Some(item) = &entry.item {
+                    output.push(item.to_string());
+                }
+                if let Some(table) = entry.table.as_ref().and_then(|t| tables.get(t)) {
+                    output.extend(table.roll_nested(tables, level, rng, depth + 1));
+                }
+            }
+        }
+        output
+    }
+}
+
+pub fn has_table_cycle(tables: &HashMap<String, LootTable>, name: &str, path: &mut Vec<String>) -> bool {
+    // depth first, through the nested tables
+    if path.iter().any(|p| p == name) { return true }
+    let Some(table) = tables.get(name) else { return false };
+    path.push(name.to_string());
+    let result = table.entries.iter()
+        .filter_map(|e| e.table.as_ref())
+        .any(|t| has_table_cycle(tables, t, path));
+    path.pop();
+    result
+}
+
+#[derive(Clone, Deserialize)]
+pub struct LootEntry {
+    // an entry with neither an item nor a table drops nothing
+    pub item: Option<String>,
+    pub table: Option<String>,
+    #[serde(default = "default_weight")]
+    pub weight: f32,
+    #[serde(default = "default_count")]
+    #[serde(deserialize_with="deserialize_count")]
+    pub count: (u32, u32),
+    #[serde(default)]
+    pub min_level: u32,
+    #[serde(default)]
+    pub max_level: u32
+}
+
+fn default_weight() -> f32 { 1. }
+
+fn default_count() -> (u32, u32) { (1, 1) }
+
+fn deserialize_count<'de, D>(d: D) -> Result<(u32, u32), D::Error>
+where D: Deserializer<'de> {
+    // the range is kept, as the tables are rolled many times
+    match serde_yaml::Value::deserialize(d)? {
+        serde_yaml::Value::Number(n) => {
+            let v = n.as_u64().ok_or(serde::de::Error::custom("Wrong value!"))? as u32;
+            Ok((v, v))
+        },
+        serde_yaml::Value::String(s) => {
+            let parts = s.split('-').collect::<Vec<_>>();
+            if parts.len() != 2 { return Err(serde::de::Error::custom("Wrong value!")) }
+            let a = parts[0].parse::<u32>().map_err(serde::de::Error::custom)?;
+            let b = parts[1].parse::<u32>().map_err(serde::de::Error::custom)?;
+            if a > b { return Err(serde::de::Error::custom("Wrong value!")) }
+            Ok((a, b))
+        },
+        _ => Err(serde::de::Error::custom("Wrong value!"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::GameData;
+
+    const ENTITIES: &str = "
+Gold:
+  sprite: { atlas_name: items, index: 0 }
+  components:
+Sword:
+  sprite: { atlas_name: items, index: 1 }
+  components:
+";
+
+    fn get_data(tables: &str) -> GameData {
+        let mut data = GameData::new();
+        data.add_entities_from_str(ENTITIES.to_string());
+        data.add_loot_tables_from_str(tables.to_string());
+        data
+    }
+
+    #[test]
+    fn roll_counts() {
+        let data = get_data("
+Coins:
+  rolls: 2-3
+  entries:
+    - { item: Gold, count: 2 }
+");
+        let mut rng = StdRng::seed_from_u64(0);
+        for _ in 0..100 {
+            let items = data.roll_loot("Coins", 1, &mut rng);
+            assert!(items.len() == 4 || items.len() == 6);
+            assert!(items.iter().all(|i| i == "Gold"));
+        }
+    }
+
+    #[test]
+    fn roll_levels() {
+        let data = get_data("
+Weapons:
+  entries:
+    - { item: Gold, max_level: 2 }
+    - { item: Sword, min_level: 3 }
+");
+        let mut rng = StdRng::seed_from_u64(0);
+        for _ in 0..100 {
+            assert_eq!(data.roll_loot("Weapons", 1, &mut rng), vec!["Gold"]);
+            assert_eq!(data.roll_loot("Weapons", 5, &mut rng), vec!["Sword"]);
+        }
+    }
+
+    #[test]
+    fn roll_nested() {
+        let data = get_data("
+Coins:
+  entries:
+    - { item: Gold }
+Chest:
+  entries:
+    - { table: Coins, count: 3 }
+");
+        let mut rng = StdRng::seed_from_u64(0);
+        assert_eq!(data.roll_loot("Chest", 1, &mut rng), vec!["Gold"; 3]);
+        assert!(data.roll_loot("Unknown", 1, &mut rng).is_empty());
+    }
+
+    #[test]
+    fn roll_cycle_is_bounded() {
+        let data = get_data("
+Loop:
+  entries:
+    - { table: Loop }
+");
+        let mut rng = StdRng::seed_from_u64(0);
+        assert!(data.roll_loot("Loop", 1, &mut rng).is_empty());
+    }
+
+    #[test]
+    fn validate_references() {
+        assert!(get_data("
+Chest:
+  entries:
+    - { item: Gold }
+    - { table: Coins }
+Coins:
+  entries:
+    - { item: Gold }
+").validate_loot_tables().is_ok());
+        assert!(get_data("
+Chest:
+  entries:
+    - { item: Shield }
+").validate_loot_tables().is_err());
+        assert!(get_data("
+Chest:
+  entries:
+    - { table: Coins }
+").validate_loot_tables().is_err());
+    }
+
+    #[test]
+    fn validate_cycles() {
+        assert!(get_data("
+A:
+  entries:
+    - { table: B }
+B:
+  entries:
+    - { table: C }
+C:
+  entries:
+    - { table: A }
+").validate_loot_tables().is_err());
+        // a table shared by two branches is not a cycle
+        assert!(get_data("
+A:
+  entries:
+    - { table: B }
+    - { table: C }
+B:
+  entries:
+    - { table: C }
+C:
+  entries:
+    - { item: Gold }
+").validate_loot_tables().is_ok());
+    }
+}
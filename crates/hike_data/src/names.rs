@@ -0,0 +1,21 @@
+use rand::prelude::*;
+
+const SYLLABLES: [&str; 24] = [
+    "ka", "zel", "mor", "thu", "vex", "lo", "ri", "qua", "nix", "bel", "dro", "esh",
+    "fa", "gor", "ith", "jun", "pra", "sul", "tor", "yam", "xo", "ul", "bra", "em"
+];
+
+pub fn get_gibberish<R: Rng>(rng: &mut R) -> String {
+    // eg. ZELMOR THUKA
+    let word_count = rng.gen_range(1..=2);
+    (0..word_count)
+        .map(|_| {
+            let syllable_count = rng.gen_range(2..=3);
+            (0..syllable_count)
+                .map(|_| *SYLLABLES.choose(rng).unwrap())
+                .collect::<String>()
+                .to_uppercase()
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
@@ -8,21 +8,27 @@ use std::{
     collections::{HashSet, VecDeque}
 };
 
-use crate::board::Board;
+use hike_data::GameData;
+
+use crate::board::{Board, replace_tile};
 use crate::components::{
-    Actor, Discoverable, Durability, Stunned, Fixture, Health, Interactive, Loot, Defensive,
+    Actor, Blessing, Discoverable, Durability, Stunned, Fixture, Health, Interactive, Loot, Defensive,
     Obstacle, Position, Player, Name, Poisoned, Effects, Projectile, Budding, Switch,
-    Swing, Immune, Lunge, Push, Offensive, Ranged, Tile, Regeneration, Immaterial, Summoner
+    Swing, Immune, Lunge, Push, Offensive, Ranged, Tile, Regeneration, Immaterial, Summoner,
+    Noise, Stealthy, Slowed, Boss, Shop, Collectable, Weapon, Equipment, Stack, Lockable, Openable, Opener, Hidden, Trap
 };
 use crate::GameStats;
-use crate::globals::MAX_COLLECTABLES;
+use crate::globals::{MAX_COLLECTABLES, MAX_WEAPONS, COMBAT_NOISE, NPC_MEMORY, SEARCH_CHANCE, SEARCH_RANGE, STEP_NOISE};
 use crate::events::GameEvent;
-use crate::player::{get_player_entity, get_player_position};
+use crate::player::{get_player_entity, get_player_position, get_worn_items};
 use crate::structs::{
-    AttackKind, InteractionKind, Attitude,
+    AttackKind, InteractionKind, Attitude, Behaviour, BlessingKind, TrapEffect,
     get_attack_action, get_effect_action
 };
-use crate::utils::{visibility, get_entities_at_position, spawn_with_position, is_hostile};
+use crate::utils::{
+    get_armor, get_entities_at_position, get_noise_range, get_stack_size, get_stack_target, get_terrain,
+    spawn_with_position, get_rng, is_cursed, is_hostile, is_resistant
+};
 
 pub struct PendingActions(pub VecDeque<Box<dyn Action>>);
 pub struct ActorQueue(pub VecDeque<Entity>);
@@ -33,6 +39,8 @@ pub trait Action {
     fn as_any(&self) -> &dyn Any;
     fn execute(&self, world: &mut World) -> ActionResult;
     fn event(&self) -> GameEvent { GameEvent::Other }
+    // published instead, when the execution fails
+    fn failure_event(&self, world: &World) -> Option<GameEvent> { None }
     fn score(&self, world: &World) -> i32 { 0 }
     fn type_id(&self) -> TypeId where Self: 'static {
         TypeId::of::<Self>()
@@ -61,16 +69,23 @@ pub fn get_action_at_dir(
     }
 
     // other actions
-    if let Some(door) = entities.iter()
-        .find(|&e| world.get_component::<Name>(*e).unwrap().0 == "Closed_Door") {
-            return Some(Box::new(Replace { entity: *door, name: "Open_Door".to_string() }))
+    if let Some(door) = entities.iter().find(|&&e| world.get_component::<Openable>(e).is_some()) {
+        if world.get_component::<Player>(entity).is_some() || can_open_at(entity, target, world) {
+            let is_locked = world.get_component::<Lockable>(*door).is_some();
+            return Some(Box::new(OpenDoor { entity, target, is_locked }))
         }
+    }
 
     let has_obstacle = entities.iter().any(|&e| world.get_component::<Obstacle>(e).is_some());
 
     if has_obstacle && world.get_component::<Immaterial>(entity).is_none() { 
         return None
     }
+    // npcs do not step into harmful terrain or known traps
+    if world.get_component::<Player>(entity).is_none()
+        && (is_harmful_terrain(world, target) || is_known_trap(world, target)) {
+        return None
+    }
     Some(Box::new(Walk { entity, target }))
 }
 
@@ -116,6 +131,10 @@ pub fn get_npc_action(
     entity: Entity,
     world: &World
 ) -> Box<dyn Action> {
+    if let Some(actor) = world.get_component::<Actor>(entity) {
+        if actor.behaviour == Behaviour::Sleeping { return Box::new(Pause) }
+    }
+
     let mut possible_actions = ORTHO_DIRECTIONS.iter()
        .filter_map(|dir| get_action_at_dir(entity, world, *dir))
        .collect::<Vec<_>>();
@@ -130,6 +149,10 @@ pub fn get_npc_action(
         }
     }
 
+    if let Some(action) = get_quaff_action(entity, world) {
+        possible_actions.push(action);
+    }
+
     possible_actions.sort_by(|a, b| a.score(world).cmp(&b.score(world)));
     match possible_actions.pop() {
         Some(a) => a,
@@ -137,6 +160,18 @@ pub fn get_npc_action(
     }
 }
 
+fn get_quaff_action(entity: Entity, world: &World) -> Option<Box<dyn Action>> {
+    // panicking npcs use the discoverables they carry
+    let actor = world.get_component::<Actor>(entity)?;
+    if actor.attitude != Attitude::Panic { return None }
+    let data = world.get_resource::<GameData>()?;
+    let loot = world.get_component::<Loot>(entity)?;
+    let name = loot.items.iter().find(|n| data.discoverables.contains(n))?.to_string();
+    let position = world.get_component::<Position>(entity)?.0;
+    let is_seen = world.get_resource::<Board>()?.visible.contains(&position);
+    Some(Box::new(Quaff { entity, name, is_seen }))
+}
+
 fn get_empty_neighboring_tile(entity: Entity, world: &World) -> Option<Vector2i> {
     let position = world.get_component::<Position>(entity)?.0;
     let pool = ORTHO_DIRECTIONS.iter()
@@ -145,10 +180,15 @@ fn get_empty_neighboring_tile(entity: Entity, world: &World) -> Option<Vector2i>
             .iter()
             .any(|&e| world.get_component::<Obstacle>(e).is_some())
         );
-    let mut rng = thread_rng();
+    let mut rng = get_rng();
     pool.choose(&mut rng)
 }
 
+fn get_noise_volume(entity: Entity, volume: u32, world: &World) -> u32 {
+    if world.get_component::<Stealthy>(entity).is_some() { return volume / 2 }
+    volume
+}
+
 pub struct Walk {
     pub entity: Entity,
     pub target: Vector2i
@@ -158,8 +198,32 @@ impl Action for Walk {
     fn execute(&self, world: &mut World) -> ActionResult {
         let mut position = world.get_component_mut::<Position>(self.entity).ok_or(())?;
         position.0 = self.target;
+        drop(position);
 
-        Ok(Vec::new())
+        let mut actions: Vec<Box<dyn Action>> = Vec::new();
+        if world.get_component::<Immaterial>(self.entity).is_none() {
+            if let Some(terrain) = get_terrain(world, self.target) {
+                // wading takes an extra turn
+                if terrain.slowing {
+                    let _ = world.insert_component(self.entity, Slowed);
+                }
+                if let Some(next) = terrain.trampled {
+                    replace_tile(world, self.target, &next);
+                }
+            }
+            actions.extend(
+                get_entities_at_position(world, self.target).iter()
+                    .filter(|&&e| world.get_component::<Trap>(e).is_some())
+                    .map(|&e| Box::new(TriggerTrap { entity: self.entity, trap: e }) as Box<dyn Action>)
+            );
+        }
+
+        // only the player's footsteps are worth listening to
+        if world.get_component::<Player>(self.entity).is_some()
+            && world.get_component::<Stealthy>(self.entity).is_none() {
+            actions.push(Box::new(MakeNoise { source: self.target, volume: STEP_NOISE }));
+        }
+        Ok(actions)
     }
     fn event(&self) -> GameEvent {
         GameEvent::Travel(self.entity, true)
@@ -172,9 +236,13 @@ impl Action for Walk {
             ) {
                 return -10
             };
-        let mut rng = thread_rng();
+        // the immaterial float over the traps
+        if is_known_trap(world, self.target)
+            && world.get_component::<Immaterial>(self.entity).is_none() {
+            return -10
+        }
+        let mut rng = get_rng();
         let r = rng.gen_range(0..4);
-        let Some(position) = world.get_component::<Position>(self.entity) else { return r };
         let Some(actor) = world.get_component::<Actor>(self.entity) else { return r };
 
         if let Some(player_v) = get_player_position(world) {
@@ -192,25 +260,54 @@ impl Action for Walk {
             }
         }
 
-        let Some(target) = actor.target else { return r };
-        let Some(board) = world.get_resource::<Board>() else { return r };
+        match is_on_target_path(self.entity, self.target, world) {
+            Some(true) => 20,
+            _ => r
+        }
+    }
+}
 
-        let blockers = match world.get_component::<Immaterial>(self.entity) {
-            Some(_) => HashSet::new(),
-            None => world.query::<Obstacle>().with::<Position>().build().iter::<Position>()
-                .map(|p| p.0)
-                .collect::<HashSet<_>>()
-        };
+fn is_on_target_path(entity: Entity, v: Vector2i, world: &World) -> Option<bool> {
+    let position = world.get_component::<Position>(entity)?;
+    let target = world.get_component::<Actor>(entity)?.target?;
+    let board = world.get_resource::<Board>()?;
 
-        let Some(path) = find_path(
-            position.0,
-            target,
-            &board.tiles.keys().map(|&v| v).collect::<HashSet<_>>(),
-            &blockers
-        ) else { return r };
+    let blockers = match world.get_component::<Immaterial>(entity) {
+        Some(_) => HashSet::new(),
+        None => world.query::<Obstacle>().with::<Position>().build().iter::<Position>()
+            .map(|p| p.0)
+            .filter(|&p| !can_open_at(entity, p, world))
+            .chain(board.tiles.keys().copied()
+                .filter(|&v| is_harmful_terrain(world, v) || is_known_trap(world, v))
+            )
+            .collect::<HashSet<_>>()
+    };
 
-        if path.contains(&self.target) { 20 } else { r }
-    }
+    let path = find_path(
+        position.0,
+        target,
+        &board.tiles.keys().map(|&v| v).collect::<HashSet<_>>(),
+        &blockers
+    )?;
+    Some(path.contains(&v))
+}
+
+fn is_harmful_terrain(world: &World, v: Vector2i) -> bool {
+    get_terrain(world, v).map_or(false, |t| t.damage > 0)
+}
+
+fn is_known_trap(world: &World, v: Vector2i) -> bool {
+    get_entities_at_position(world, v).iter()
+        .any(|&e| world.get_component::<Trap>(e).is_some() && world.get_component::<Hidden>(e).is_none())
+}
+
+fn can_open_at(entity: Entity, v: Vector2i, world: &World) -> bool {
+    // locks and tolls are meant for the player only
+    if world.get_component::<Opener>(entity).is_none() { return false }
+    get_entities_at_position(world, v).iter()
+        .any(|&e| world.get_component::<Openable>(e).map_or(false, |o| o.cost.is_none())
+            && world.get_component::<Lockable>(e).is_none()
+        )
 }
 
 pub struct AttackAction {
@@ -239,7 +336,9 @@ impl AttackAction {
                 .collect::<Vec<_>>();
             output.extend(vs);
         }
-        if world.get_component::<Lunge>(entity).is_some() {
+        // lunging is not possible when stuck
+        let is_stuck = get_terrain(world, position.0).map_or(false, |t| t.sticky);
+        if world.get_component::<Lunge>(entity).is_some() && !is_stuck {
             output.insert(position.0 + dir * 2);
         }
 
@@ -285,6 +384,15 @@ impl Action for AttackAction {
         if actions.len() > 0 { actions.push(Box::new(
             Defend { attacker: self.entity, target: self.target }
         ))};
+
+        // do not make noise when hitting an empty tile (eg. traps)
+        if get_entities_at_position(world, self.target).iter()
+            .any(|&e| world.get_component::<Health>(e).is_some()) {
+                actions.push(Box::new(MakeNoise {
+                    source: self.target,
+                    volume: get_noise_volume(self.entity, COMBAT_NOISE, world)
+                }));
+            }
         
         if world.get_component::<Durability>(offending_entity).is_some() {
             actions.push(Box::new(
@@ -316,9 +424,14 @@ pub struct Defend {
 }
 impl Defend {
     fn get_defensive_actions(&self, entity: Entity, world: &World, target: Vector2i) -> Vec<Box<dyn Action>> {
-        let Some(defensive) = world.get_component::<Defensive>(entity) else { return Vec::new() };
-        defensive.attacks.iter()
-            .map(|a| get_attack_action(a, target))
+        // worn equipment can strike back as well
+        get_worn_items(world, entity).into_iter()
+            .chain([entity])
+            .filter_map(|e| world.get_component::<Defensive>(e))
+            .flat_map(|d| d.attacks.iter()
+                .map(|a| get_attack_action(a, target))
+                .collect::<Vec<_>>()
+            )
             .collect()
     }
 }
@@ -344,14 +457,20 @@ pub struct HitAction {
 impl Action for HitAction {
     fn as_any(&self) -> &dyn Any { self }
     fn execute(&self, world: &mut World) -> ActionResult {
-        let actions = get_entities_at_position(world, self.target).iter()
-            .filter_map(|e| match world.get_component::<Health>(*e) {
-                Some(_) => Some(
-                    Box::new(Damage { entity: *e, value: self.value }) as Box<dyn Action>
-                ),
-                None => None
-            })
-            .collect::<Vec<_>>();
+        let mut actions: Vec<Box<dyn Action>> = Vec::new();
+        for entity in get_entities_at_position(world, self.target) {
+            if world.get_component::<Health>(entity).is_none() { continue }
+            let value = self.value.saturating_sub(get_armor(entity, world));
+            if value > 0 {
+                actions.push(Box::new(Damage { entity, value }));
+            }
+            // worn equipment wears out when hit
+            actions.extend(
+                get_worn_items(world, entity).into_iter()
+                    .filter(|&e| world.get_component::<Durability>(e).is_some())
+                    .map(|e| Box::new(TakeDurability { entity: e, owner: entity }) as Box<dyn Action>)
+            );
+        }
         Ok(actions)
     }
     // no score - should be a resulting action only
@@ -366,6 +485,7 @@ impl Action for StunAction {
     fn execute(&self, world: &mut World) -> ActionResult {
         for entity in get_entities_at_position(world, self.target) {
             if world.get_component::<Health>(entity).is_none() { continue }
+            if is_resistant(entity, AttackKind::Stun, world) { continue }
             if let Some(mut stunned)  = world.get_component_mut::<Stunned>(entity) {
                 stunned.0 += self.value;
                 continue
@@ -387,6 +507,7 @@ impl Action for PoisonAction {
         let mut actions = Vec::new();
         for entity in get_entities_at_position(world, self.target) {
             if world.get_component::<Health>(entity).is_none() { continue }
+            if is_resistant(entity, AttackKind::Poison, world) { continue }
             actions.push(Box::new(
                 ApplyPoison { entity, value: self.value }
             ) as Box<dyn Action>);
@@ -484,8 +605,15 @@ pub struct TakeDurability {
 impl Action for TakeDurability {
     fn as_any(&self) -> &dyn Any { self }
     fn execute(&self, world: &mut World) -> ActionResult {
+        let mut rng = get_rng();
+        let loss = match world.get_component::<Blessing>(self.entity).map(|b| b.kind) {
+            Some(BlessingKind::Cursed) => 2,
+            // blessed weapons wear out half as fast
+            Some(BlessingKind::Blessed) => rng.gen_range(0..=1),
+            None => 1
+        };
         if let Some(mut durability) = world.get_component_mut::<Durability>(self.entity) {
-            durability.0 = durability.0.saturating_sub(1);
+            durability.0 = durability.0.saturating_sub(loss);
         }
         Ok(Vec::new())
     }
@@ -508,24 +636,65 @@ impl Action for WieldWeapon {
     fn execute(&self, world: &mut World) -> ActionResult {
         let mut replaced = None;
         if let Some(mut player) = world.query::<Player>().build().single_mut::<Player>() {
-            let index = if let Some(free) = player.weapons.iter()
-                .enumerate()
-                .filter(|a| a.1.is_none())
-                .next() {
-                    free.0
-                } else {
-                    player.active_weapon
-                };
+            // cursed weapons cannot be replaced
+            let is_locked = |i: usize| player.weapons[i].map_or(false, |e| is_cursed(e, world));
+            let index = match player.weapons.iter().position(|w| w.is_none()) {
+                Some(free) => free,
+                None if !is_locked(player.active_weapon) => player.active_weapon,
+                None => (0..MAX_WEAPONS).find(|&i| !is_locked(i)).ok_or(())?
+            };
     
             replaced = player.weapons[index];
             player.weapons[index] = Some(self.entity);
         };
 
+        // the replaced weapon is left in place of the new one
+        let position = world.get_component::<Position>(self.entity).map(|p| p.0);
+        world.remove_component::<Position>(self.entity);
+        if let (Some(replaced), Some(position)) = (replaced, position) {
+            let _ = world.insert_component(replaced, Position(position));
+        }
+
+        if let Some(blessing) = world.get_component::<Blessing>(self.entity) {
+            if !blessing.known {
+                return Ok(vec![Box::new(RevealBlessing { entity: self.entity })])
+            }
+        }
+        Ok(Vec::new())
+    }
+    // no score - npcs do not pick
+}
+
+pub struct Equip {
+    pub entity: Entity
+}
+impl Action for Equip {
+    fn as_any(&self) -> &dyn Any { self }
+    fn event(&self) -> GameEvent {
+        GameEvent::PickItem
+    }
+    fn execute(&self, world: &mut World) -> ActionResult {
+        let slot = world.get_component::<Equipment>(self.entity).ok_or(())?.slot as usize;
+        let player_entity = get_player_entity(world).ok_or(())?;
+        let mut player = world.get_component_mut::<Player>(player_entity).ok_or(())?;
+        let replaced = player.equipment[slot];
+        // cursed items cannot be taken off
+        if replaced.map_or(false, |e| is_cursed(e, world)) { return Err(()) }
+        player.equipment[slot] = Some(self.entity);
+        drop(player);
+
+        // the replaced item is left in place of the new one
+        let position = world.get_component::<Position>(self.entity).map(|p| p.0);
         world.remove_component::<Position>(self.entity);
-        if let Some(replaced) = replaced {
-            world.despawn_entity(replaced);
+        if let (Some(replaced), Some(position)) = (replaced, position) {
+            let _ = world.insert_component(replaced, Position(position));
         }
 
+        if let Some(blessing) = world.get_component::<Blessing>(self.entity) {
+            if !blessing.known {
+                return Ok(vec![Box::new(RevealBlessing { entity: self.entity })])
+            }
+        }
         Ok(Vec::new())
     }
     // no score - npcs do not pick
@@ -540,6 +709,25 @@ impl Action for PickCollectable {
         GameEvent::PickItem
     }
     fn execute(&self, world: &mut World) -> ActionResult {
+        if let Some(target) = get_stack_target(self.entity, world) {
+            let count = get_stack_size(self.entity, world);
+            if let Some(mut stack) = world.get_component_mut::<Stack>(target) {
+                stack.0 += count;
+            } else {
+                let _ = world.insert_component(target, Stack(1 + count));
+            }
+            // the stack shares a single state, so a hidden curse is not washed out
+            let hidden = world.get_component::<Blessing>(self.entity)
+                .map(|b| Blessing { kind: b.kind, known: b.known });
+            if let Some(blessing) = hidden {
+                if world.get_component::<Blessing>(target).is_none() {
+                    let _ = world.insert_component(target, blessing);
+                }
+            }
+            world.despawn_entity(self.entity);
+            return Ok(Vec::new())
+        }
+
         if let Some(mut player) = world.query::<Player>().build().single_mut::<Player>() {
             if player.collectables.len() >= MAX_COLLECTABLES {
                 return Err(())
@@ -554,6 +742,83 @@ impl Action for PickCollectable {
     // no score - npcs do not pick
 }
 
+pub struct DropItem {
+    pub entity: Entity
+}
+impl Action for DropItem {
+    fn as_any(&self) -> &dyn Any { self }
+    fn failure_event(&self, world: &World) -> Option<GameEvent> {
+        // the curse is revealed by the refused drop
+        if !is_cursed(self.entity, world) { return None }
+        let player = world.get_component::<Player>(get_player_entity(world)?)?;
+        let is_worn = player.weapons.contains(&Some(self.entity))
+            || player.equipment.contains(&Some(self.entity));
+        if is_worn { Some(GameEvent::Reveal(self.entity)) } else { None }
+    }
+    fn execute(&self, world: &mut World) -> ActionResult {
+        let player_entity = get_player_entity(world).ok_or(())?;
+        let position = get_player_position(world).ok_or(())?;
+        let mut player = world.get_component_mut::<Player>(player_entity).ok_or(())?;
+
+        if let Some(slot) = player.weapons.iter_mut().find(|w| **w == Some(self.entity)) {
+            // cursed items cannot be taken off
+            if is_cursed(self.entity, world) { return reveal_curse(self.entity, world) }
+            *slot = None;
+        } else if let Some(slot) = player.equipment.iter_mut().find(|w| **w == Some(self.entity)) {
+            if is_cursed(self.entity, world) { return reveal_curse(self.entity, world) }
+            *slot = None;
+        } else if player.collectables.contains(&self.entity) {
+            // the whole stack is dropped
+            player.collectables.retain(|&e| e != self.entity);
+        } else {
+            return Err(())
+        }
+        drop(player);
+
+        let _ = world.insert_component(self.entity, Position(position));
+        Ok(Vec::new())
+    }
+    // no score - npcs do not drop
+}
+
+fn reveal_curse(entity: Entity, world: &World) -> ActionResult {
+    if let Some(mut blessing) = world.get_component_mut::<Blessing>(entity) {
+        blessing.known = true;
+    }
+    Err(())
+}
+
+pub struct SwapCollectable {
+    // item on the floor
+    pub entity: Entity,
+    // inventory slot to put down
+    pub index: usize
+}
+impl Action for SwapCollectable {
+    fn as_any(&self) -> &dyn Any { self }
+    fn event(&self) -> GameEvent {
+        GameEvent::PickItem
+    }
+    fn execute(&self, world: &mut World) -> ActionResult {
+        // only the collectables lying under the player can be picked
+        if world.get_component::<Collectable>(self.entity).is_none() { return Err(()) }
+        let position = world.get_component::<Position>(self.entity).ok_or(())?.0;
+        if Some(position) != get_player_position(world) { return Err(()) }
+        let player_entity = get_player_entity(world).ok_or(())?;
+        let mut player = world.get_component_mut::<Player>(player_entity).ok_or(())?;
+        if player.collectables.contains(&self.entity) { return Err(()) }
+        let slot = player.collectables.get_mut(self.index).ok_or(())?;
+        let dropped = *slot;
+        *slot = self.entity;
+        drop(player);
+
+        world.remove_component::<Position>(self.entity);
+        let _ = world.insert_component(dropped, Position(position));
+        Ok(Vec::new())
+    }
+    // no score - npcs do not pick
+}
+
 pub struct UseCollectable {
     pub entity: Entity
 }
@@ -566,14 +831,23 @@ impl Action for UseCollectable {
         let mut actions: Vec<Box<dyn Action>> = Vec::new();
         let player_query = world.query::<Player>().build();
         let Some(player_entity) = player_query.single_entity() else { return Err(()) };
+        let blessing = world.get_component::<Blessing>(self.entity).map(|b| b.kind);
         if let Some(effects) = world.get_component::<Effects>(self.entity) {
             actions.extend(
                 effects.effects.iter()
-                    .map(|e| get_effect_action(e, player_entity))
+                    .map(|e| get_effect_action(&e.with_blessing(blessing), player_entity))
             );
         }
+        // take a single item off the stack
+        let mut is_depleted = true;
+        if let Some(mut stack) = world.get_component_mut::<Stack>(self.entity) {
+            stack.0 = stack.0.saturating_sub(1);
+            is_depleted = stack.0 == 0;
+        }
         if let Some(mut player) = player_query.single_mut::<Player>() {
-            player.collectables.retain(|&e| e != self.entity);
+            if is_depleted {
+                player.collectables.retain(|&e| e != self.entity);
+            }
 
             if world.get_component::<Discoverable>(self.entity).is_some() {
                 if let Some(name) = world.get_component::<Name>(self.entity) {
@@ -581,7 +855,9 @@ impl Action for UseCollectable {
                 }
             }
         };
-        world.despawn_entity(self.entity);
+        if is_depleted {
+            world.despawn_entity(self.entity);
+        }
 
         Ok(actions)
     }
@@ -624,6 +900,14 @@ impl Action for Damage {
         }
         let mut health = world.get_component_mut::<Health>(self.entity).ok_or(())?;
         health.0.current = health.0.current.saturating_sub(self.value);
+        if let Some(mut actor) = world.get_component_mut::<Actor>(self.entity) {
+            if actor.behaviour == Behaviour::Sleeping {
+                // rude awakening
+                actor.behaviour = Behaviour::Idle;
+                actor.attitude = Attitude::Hostile;
+                actor.memory = NPC_MEMORY;
+            }
+        }
         if world.get_component::<Budding>(self.entity).is_some() {
             return Ok(vec![Box::new(BuddingActon { entity: self.entity })])
         }
@@ -730,6 +1014,61 @@ impl Action for GiveRegeneration {
     // score is not implemented as it always should be a resulting action
 }
 
+pub struct GiveStealth {
+    pub entity: Entity,
+    pub value: u32
+}
+impl Action for GiveStealth {
+    fn as_any(&self) -> &dyn Any { self }
+    fn event(&self) -> GameEvent {
+        GameEvent::Stealth(self.entity)
+    }
+    fn execute(&self, world: &mut World) -> ActionResult {
+        if let Some(mut stealthy) = world.get_component_mut::<Stealthy>(self.entity) {
+            stealthy.0 += self.value;
+            return Ok(Vec::new())
+        };
+
+        let _ = world.insert_component(self.entity, Stealthy(self.value));
+        Ok(Vec::new())
+    }
+    // score is not implemented as it always should be a resulting action
+}
+
+pub struct MakeNoise {
+    pub source: Vector2i,
+    pub volume: u32
+}
+impl Action for MakeNoise {
+    fn as_any(&self) -> &dyn Any { self }
+    fn execute(&self, world: &mut World) -> ActionResult {
+        let heard = get_noise_range(world, self.source, self.volume);
+        let query = world.query::<Actor>().with::<Position>().build();
+        for ((mut actor, position), &entity) in query.iter_mut::<Actor>()
+            .zip(query.iter::<Position>())
+            .zip(query.entities())
+        {
+            if !heard.contains(&position.0) { continue }
+            if world.get_component::<Player>(entity).is_some() { continue }
+            if actor.behaviour == Behaviour::Sleeping {
+                // footsteps are not loud enough to wake anybody up
+                if self.volume <= STEP_NOISE { continue }
+                actor.behaviour = Behaviour::Idle;
+            }
+            match actor.attitude {
+                Attitude::Panic => continue,
+                Attitude::Neutral => actor.attitude = Attitude::Aware,
+                _ => ()
+            }
+            // go and check what has happened
+            actor.target = Some(self.source);
+            actor.memory = NPC_MEMORY;
+        }
+        Ok(Vec::new())
+    }
+    // score is not implemented as it always should be a resulting action
+}
+
 pub struct Repair {
     pub entity: Entity,
     pub value: u32
@@ -765,6 +1104,125 @@ impl Action for UpgradeHealth {
     // score is not implemented as it always should be a resulting action
 }
 
+pub struct UpgradeOffensive {
+    pub entity: Entity,
+    pub value: u32
+}
+impl Action for UpgradeOffensive {
+    fn as_any(&self) -> &dyn Any { self }
+    fn event(&self) -> GameEvent {
+        GameEvent::Enchant(self.entity, self.value)
+    }
+    fn execute(&self, world: &mut World) -> ActionResult {
+        let mut offensive = world.get_component_mut::<Offensive>(self.entity).ok_or(())?;
+        for attack in offensive.attacks.iter_mut() {
+            attack.value += self.value;
+        }
+        Ok(Vec::new())
+    }
+    // score is not implemented as it always should be a resulting action
+}
+
+pub struct Identify {
+    pub entity: Entity
+}
+impl Action for Identify {
+    fn as_any(&self) -> &dyn Any { self }
+    fn event(&self) -> GameEvent {
+        GameEvent::Identify(self.entity)
+    }
+    fn execute(&self, world: &mut World) -> ActionResult {
+        if let Some(mut blessing) = world.get_component_mut::<Blessing>(self.entity) {
+            blessing.known = true;
+        }
+        let name = world.get_component::<Name>(self.entity).ok_or(())?.0.clone();
+        let player_entity = get_player_entity(world).ok_or(())?;
+        let mut player = world.get_component_mut::<Player>(player_entity).ok_or(())?;
+        player.discovered.insert(name);
+        Ok(Vec::new())
+    }
+    // score is not implemented as it always should be a resulting action
+}
+
+pub struct RevealBlessing {
+    pub entity: Entity
+}
+impl Action for RevealBlessing {
+    fn as_any(&self) -> &dyn Any { self }
+    fn event(&self) -> GameEvent {
+        GameEvent::Reveal(self.entity)
+    }
+    fn execute(&self, world: &mut World) -> ActionResult {
+        let mut blessing = world.get_component_mut::<Blessing>(self.entity).ok_or(())?;
+        blessing.known = true;
+        Ok(Vec::new())
+    }
+    // score is not implemented as it always should be a resulting action
+}
+
+pub struct Gamble {
+    pub entity: Entity,
+    pub value: u32,
+    // outcome is rolled upfront so the event can tell the result
+    outcome: u32
+}
+impl Gamble {
+    pub fn new(entity: Entity, value: u32) -> Self {
+        let mut rng = get_rng();
+        Gamble { entity, value, outcome: rng.gen_range(0..6) }
+    }
+    fn is_win(&self) -> bool {
+        self.outcome < 3
+    }
+}
+impl Action for Gamble {
+    fn as_any(&self) -> &dyn Any { self }
+    fn event(&self) -> GameEvent {
+        GameEvent::Gamble(self.entity, self.is_win())
+    }
+    fn execute(&self, world: &mut World) -> ActionResult {
+        // the shrine should never be lethal, so the losses are capped by the health left
+        // the poison deals a point of damage per turn, the pending one included
+        let current = world.get_component::<Health>(self.entity).ok_or(())?.0.current;
+        let poisoned = world.get_component::<Poisoned>(self.entity).map_or(0, |p| p.0);
+        let spare = current.saturating_sub(1).saturating_sub(poisoned);
+        let action: Box<dyn Action> = match self.outcome {
+            0 => Box::new(PickGold { value: 2 * self.value }),
+            1 => Box::new(Heal { entity: self.entity, value: self.value }),
+            2 => Box::new(GiveImmunity { entity: self.entity, value: self.value }),
+            3 | 4 if self.value.min(spare) == 0 => return Ok(Vec::new()),
+            3 => Box::new(ApplyPoison { entity: self.entity, value: self.value.min(spare) }),
+            4 => Box::new(Damage { entity: self.entity, value: self.value.min(spare) }),
+            // the house takes it all
+            _ => return Ok(Vec::new())
+        };
+        Ok(vec![action])
+    }
+    // score is not implemented as it always should be a resulting action
+}
+
+pub struct Sacrifice {
+    pub entity: Entity,
+    pub altar: Entity,
+    pub value: u32
+}
+impl Action for Sacrifice {
+    fn as_any(&self) -> &dyn Any { self }
+    fn event(&self) -> GameEvent {
+        GameEvent::Sacrifice(self.entity, self.value)
+    }
+    fn execute(&self, world: &mut World) -> ActionResult {
+        let mut health = world.get_component_mut::<Health>(self.entity).ok_or(())?;
+        // the sacrifice should never be lethal
+        if health.0.max <= self.value { return Err(()) }
+        health.0.max -= self.value;
+        health.0.current = health.0.current.min(health.0.max);
+        drop(health);
+        Ok(get_loot_actions(self.altar, world))
+    }
+    // score is not implemented as it always should be a resulting action
+}
+
 pub struct PickGold {
     pub value: u32
 }
@@ -779,6 +1237,20 @@ impl Action for PickGold {
     // score is not implemented as it always should be a resulting action
 }
 
+pub struct PickKey {
+    pub value: u32
+}
+impl Action for PickKey {
+    fn as_any(&self) -> &dyn Any { self }
+    fn execute(&self, world: &mut World) -> ActionResult {
+        let entity = get_player_entity(world).ok_or(())?;
+        let mut player = world.get_component_mut::<Player>(entity).ok_or(())?;
+        player.keys += self.value;
+        Ok(Vec::new())
+    }
+    // score is not implemented as it always should be a resulting action
+}
+
 pub struct Pay {
     pub value: u32
 }
@@ -793,11 +1265,57 @@ impl Action for Pay {
     // score is not implemented as it always should be a resulting action
 }
 
+pub struct Buy {
+    // shop entity
+    pub entity: Entity,
+    pub index: usize
+}
+impl Action for Buy {
+    fn as_any(&self) -> &dyn Any { self }
+    fn execute(&self, world: &mut World) -> ActionResult {
+        let player_entity = get_player_entity(world).ok_or(())?;
+        let player_v = get_player_position(world).ok_or(())?;
+        let player = world.get_component::<Player>(player_entity).ok_or(())?;
+        let gold = player.gold;
+        let free_weapon = player.weapons.iter().any(|w| w.is_none());
+        let equipment = player.equipment;
+        let free_collectable = player.collectables.len() < MAX_COLLECTABLES;
+        drop(player);
+
+        let shop = world.get_component::<Shop>(self.entity).ok_or(())?;
+        let offer = shop.stock.get(self.index).ok_or(())?.clone();
+        drop(shop);
+        if offer.price > gold { return Err(()) }
+
+        // the offer is only taken off the stock once the item exists
+        let entity = spawn_with_position(world, &offer.name, player_v).ok_or(())?;
+        world.get_component_mut::<Shop>(self.entity).ok_or(())?.stock.remove(self.index);
+
+        let mut res: Vec<Box<dyn Action>> = vec![Box::new(Pay { value: offer.price })];
+        // put the item straight into the inventory if there is space left
+        // otherwise it stays on the floor
+        let equipment_slot = world.get_component::<Equipment>(entity).map(|e| e.slot as usize);
+        if world.get_component::<Weapon>(entity).is_some() && free_weapon {
+            res.push(Box::new(WieldWeapon { entity }));
+        } else if equipment_slot.map_or(false, |i| equipment[i].is_none()) {
+            res.push(Box::new(Equip { entity }));
+        } else if world.get_component::<Collectable>(entity).is_some()
+            && (free_collectable || get_stack_target(entity, world).is_some()) {
+            res.push(Box::new(PickCollectable { entity }));
+        }
+        Ok(res)
+    }
+    // score is not implemented as npcs do not trade
+}
+
 pub struct Interact {
     pub entity: Entity
 }
 impl Action for Interact {
     fn as_any(&self) -> &dyn Any { self }
+    fn failure_event(&self, _world: &World) -> Option<GameEvent> {
+        Some(GameEvent::Refused(self.entity))
+    }
     fn execute(&self, world: &mut World) -> ActionResult {
         let mut res: Vec<Box< dyn Action>> = Vec::new();
         let interactive = world.get_component::<Interactive>(self.entity).ok_or(())?;
@@ -812,24 +1330,70 @@ impl Action for Interact {
         }
 
         let action: Box<dyn Action> = match interactive.kind {
-            InteractionKind::Ascend => Box::new(Ascend),
+            InteractionKind::Ascend => {
+                let data = world.get_resource::<GameData>().ok_or(())?;
+                let board = world.get_resource::<Board>().ok_or(())?;
+                Box::new(Ascend { branch: board.get_next_branch(&data) })
+            },
+            InteractionKind::Branch(ref branch) => Box::new(Ascend { branch: Some(branch.clone()) }),
+            InteractionKind::Descend => Box::new(Descend),
+            InteractionKind::Gamble(value) => {
+                let player_entity = world.query::<Player>().build().single_entity().ok_or(())?;
+                Box::new(Gamble::new(player_entity, value))
+            },
+            InteractionKind::Open => Box::new(Open { entity: self.entity, is_locked: false }),
+            InteractionKind::Unlock => {
+                if player.keys == 0 { return Err(()) }
+                Box::new(Open { entity: self.entity, is_locked: true })
+            },
+            InteractionKind::Identify => {
+                // identify the first unknown item in the inventory
+                // any item is accepted, so the sage's answer gives nothing away
+                let mut items = player.collectables.iter()
+                    .chain(player.weapons.iter().flatten())
+                    .chain(player.equipment.iter().flatten());
+                let item = items.clone()
+                    .find(|&&e| {
+                        let is_discovered = world.get_component::<Discoverable>(e).is_none()
+                            || world.get_component::<Name>(e)
+                                .map(|n| player.discovered.contains(&n.0))
+                                .unwrap_or(true);
+                        let is_known = world.get_component::<Blessing>(e)
+                            .map(|b| b.known)
+                            .unwrap_or(true);
+                        !is_discovered || !is_known
+                    })
+                    .or(player.weapons[player.active_weapon].as_ref())
+                    .or_else(|| items.next())
+                    .ok_or(())?;
+                Box::new(Identify { entity: *item })
+            },
             InteractionKind::Repair(value) => {
                 Box::new(Repair { entity: player.weapons[player.active_weapon].ok_or(())?, value } )
             },
-            // InteractionKind::UpgradeOffensive(value) => {
-            //     let idx = world.query::<Player>().iter().next().ok_or(())?
-            //         .get::<Player>().unwrap().active_item;
-            //     let item = world.query::<Player>().iter().next().ok_or(())?
-            //         .get::<Player>().unwrap().items[idx].ok_or(())?;
-            //     Box::new(UpgradeOffensive { entity: item, value } )
-            // },
+            InteractionKind::Sacrifice(value) => {
+                let player_entity = world.query::<Player>().build().single_entity().ok_or(())?;
+                let health = world.get_component::<Health>(player_entity).ok_or(())?;
+                if health.0.max <= value { return Err(()) }
+                Box::new(Sacrifice { entity: player_entity, altar: self.entity, value } )
+            },
             InteractionKind::UpgradeHealth(value) => {
                 let player_entity = world.query::<Player>().build().single_entity().ok_or(())?;
                 Box::new(UpgradeHealth { entity: player_entity, value } )
+            },
+            InteractionKind::UpgradeOffensive(value) => {
+                let weapon = player.weapons[player.active_weapon].ok_or(())?;
+                if world.get_component::<Offensive>(weapon).is_none() { return Err(()) }
+                Box::new(UpgradeOffensive { entity: weapon, value } )
             }
         };
         res.push(action);
-        if let Some(next) = &interactive.next {
+        // otherwise replaced after the loot is dropped
+        let drops_loot = matches!(
+            interactive.kind,
+            InteractionKind::Open | InteractionKind::Unlock | InteractionKind::Sacrifice(_)
+        );
+        if let Some(next) = interactive.next.as_ref().filter(|_| !drops_loot) {
             res.push(Box::new(Replace {
                 entity: self.entity, name: next.to_string()
             }));
@@ -838,6 +1402,52 @@ impl Action for Interact {
     }
 }
 
+pub struct OpenDoor {
+    pub entity: Entity,
+    pub target: Vector2i,
+    pub is_locked: bool
+}
+impl Action for OpenDoor {
+    fn as_any(&self) -> &dyn Any { self }
+    fn event(&self) -> GameEvent {
+        match self.is_locked {
+            true => GameEvent::Open(true),
+            false => GameEvent::Other
+        }
+    }
+    fn failure_event(&self, world: &World) -> Option<GameEvent> {
+        world.get_component::<Player>(self.entity)?;
+        get_entities_at_position(world, self.target).into_iter()
+            .find(|&e| world.get_component::<Openable>(e).is_some())
+            .map(GameEvent::Refused)
+    }
+    fn execute(&self, world: &mut World) -> ActionResult {
+        let door = *get_entities_at_position(world, self.target).iter()
+            .find(|&&e| world.get_component::<Openable>(e).is_some())
+            .ok_or(())?;
+        let openable = world.get_component::<Openable>(door).ok_or(())?;
+        let name = openable.next.clone();
+        let cost = openable.cost.unwrap_or(0);
+        drop(openable);
+        let keys = world.get_component::<Lockable>(door).map_or(0, |l| l.keys);
+
+        if keys > 0 || cost > 0 {
+            let mut player = world.get_component_mut::<Player>(self.entity).ok_or(())?;
+            if player.keys < keys || player.gold < cost { return Err(()) }
+            player.keys -= keys;
+            player.gold -= cost;
+        }
+        Replace { entity: door, name }.execute(world)
+    }
+    fn score(&self, world: &World) -> i32 {
+        // npcs open the doors only when chasing a target
+        match is_on_target_path(self.entity, self.target, world) {
+            Some(true) => 20,
+            _ => -200
+        }
+    }
+}
+
 pub struct Replace {
     pub entity: Entity,
     pub name: String
@@ -846,9 +1456,13 @@ impl Action for Replace {
     fn as_any(&self) -> &dyn Any { self }
     fn execute(&self, world: &mut World) -> ActionResult {
         let position = world.get_component::<Position>(self.entity).ok_or(())?.0;
+        let mut actions: Vec<Box<dyn Action>> = Vec::new();
+        if let Some(noise) = world.get_component::<Noise>(self.entity) {
+            actions.push(Box::new(MakeNoise { source: position, volume: noise.0 }));
+        }
         world.despawn_entity(self.entity);
         spawn_with_position(world, &self.name, position);
-        Ok(Vec::new())
+        Ok(actions)
     }
     fn score(&self, world: &World) -> i32 {
         // npcs should not do those things :)
@@ -856,6 +1470,52 @@ impl Action for Replace {
     }
 }
 
+pub struct NextPhase {
+    pub entity: Entity,
+    pub position: Vector2i
+}
+impl Action for NextPhase {
+    fn as_any(&self) -> &dyn Any { self }
+    fn event(&self) -> GameEvent {
+        GameEvent::BossPhase(self.position)
+    }
+    fn execute(&self, world: &mut World) -> ActionResult {
+        let boss = world.get_component::<Boss>(self.entity).ok_or(())?;
+        let next = boss.phase.as_ref().ok_or(())?.next.to_string();
+        drop(boss);
+
+        let health = world.get_component::<Health>(self.entity).ok_or(())?;
+        let (current, max) = (health.0.current, health.0.max);
+        drop(health);
+        let target = world.get_component::<Actor>(self.entity).and_then(|a| a.target);
+        // statuses carry over as well
+        let poisoned = world.get_component::<Poisoned>(self.entity).map(|c| c.0);
+        let stunned = world.get_component::<Stunned>(self.entity).map(|c| c.0);
+        let immune = world.get_component::<Immune>(self.entity).map(|c| c.0);
+        let regeneration = world.get_component::<Regeneration>(self.entity).map(|c| c.0);
+
+        world.despawn_entity(self.entity);
+        let entity = spawn_with_position(world, &next, self.position).ok_or(())?;
+
+        if let Some(v) = poisoned { let _ = world.insert_component(entity, Poisoned(v)); }
+        if let Some(v) = stunned { let _ = world.insert_component(entity, Stunned(v)); }
+        if let Some(v) = immune { let _ = world.insert_component(entity, Immune(v)); }
+        if let Some(v) = regeneration { let _ = world.insert_component(entity, Regeneration(v)); }
+
+        // the health bar carries over between the phases
+        if let Some(mut health) = world.get_component_mut::<Health>(entity) {
+            health.0.current = current;
+            health.0.max = max;
+        }
+        if let Some(mut actor) = world.get_component_mut::<Actor>(entity) {
+            actor.attitude = Attitude::Hostile;
+            actor.target = target;
+            actor.memory = NPC_MEMORY;
+        }
+        Ok(Vec::new())
+    }
+}
+
 pub struct BuddingActon {
     pub entity: Entity
 }
@@ -879,6 +1539,76 @@ impl Action for BuddingActon {
     }
 }
 
+pub struct TriggerTrap {
+    // the entity that stepped on the trap
+    pub entity: Entity,
+    pub trap: Entity
+}
+impl Action for TriggerTrap {
+    fn as_any(&self) -> &dyn Any { self }
+    fn event(&self) -> GameEvent {
+        GameEvent::TriggerTrap(self.entity)
+    }
+    fn execute(&self, world: &mut World) -> ActionResult {
+        let position = world.get_component::<Position>(self.trap).ok_or(())?.0;
+        let effects = world.get_component::<Trap>(self.trap).ok_or(())?.effects.clone();
+        world.remove_component::<Hidden>(self.trap);
+
+        let mut actions: Vec<Box<dyn Action>> = Vec::new();
+        for effect in effects {
+            match effect {
+                TrapEffect::Darts(attack) => actions.push(get_attack_action(&attack, position)),
+                TrapEffect::Alarm(volume) => actions.push(Box::new(MakeNoise { source: position, volume })),
+                TrapEffect::Summon(name) => {
+                    let Some(v) = get_empty_neighboring_tile(self.trap, world) else { continue };
+                    let _ = spawn_with_position(world, &name, v);
+                },
+                TrapEffect::Hazard(name) => {
+                    let _ = spawn_with_position(world, &name, position);
+                }
+            }
+        }
+        // a sprung trap is spent
+        world.despawn_entity(self.trap);
+        Ok(actions)
+    }
+    // score is not implemented as it always should be a resulting action
+}
+
+pub struct Search {
+    pub entity: Entity
+}
+impl Action for Search {
+    fn as_any(&self) -> &dyn Any { self }
+    fn execute(&self, world: &mut World) -> ActionResult {
+        let mut rng = get_rng();
+        let position = world.get_component::<Position>(self.entity).ok_or(())?.0;
+        let query = world.query::<Hidden>().with::<Position>().build();
+        Ok(query.iter::<Position>().zip(query.entities())
+            .filter(|(p, _)| p.0.manhattan(position) <= SEARCH_RANGE && rng.gen_bool(SEARCH_CHANCE))
+            .map(|(_, &entity)| Box::new(RevealTrap { entity }) as Box<dyn Action>)
+            .collect()
+        )
+    }
+    // score is not implemented as npcs do not search
+}
+
+pub struct RevealTrap {
+    pub entity: Entity
+}
+impl Action for RevealTrap {
+    fn as_any(&self) -> &dyn Any { self }
+    fn event(&self) -> GameEvent {
+        GameEvent::FindTrap(self.entity)
+    }
+    fn execute(&self, world: &mut World) -> ActionResult {
+        if world.get_component::<Hidden>(self.entity).is_none() { return Err(()) }
+        world.remove_component::<Hidden>(self.entity);
+        Ok(Vec::new())
+    }
+    // score is not implemented as it always should be a resulting action
+}
+
 pub struct Summon {
     pub entity: Entity
 }
@@ -906,33 +1636,126 @@ impl Action for Summon {
     }
 }
 
-pub struct Ascend;
+pub struct Quaff {
+    pub entity: Entity,
+    pub name: String,
+    pub is_seen: bool
+}
+impl Action for Quaff {
+    fn as_any(&self) -> &dyn Any { self }
+    fn event(&self) -> GameEvent {
+        GameEvent::Quaff(self.entity, self.is_seen)
+    }
+    fn execute(&self, world: &mut World) -> ActionResult {
+        let data = world.get_resource::<GameData>().ok_or(())?;
+        let components = data.entities.get(&self.name).ok_or(())?.components.clone();
+        drop(data);
+        let value = components.get("Effects").ok_or(())?.clone();
+        let effects = serde_yaml::from_value::<Effects>(value).map_err(|_| ())?;
+
+        let mut loot = world.get_component_mut::<Loot>(self.entity).ok_or(())?;
+        let idx = loot.items.iter().position(|n| *n == self.name).ok_or(())?;
+        loot.items.remove(idx);
+        drop(loot);
+
+        // the effect is identified when the player witnesses it
+        if self.is_seen {
+            let player_entity = get_player_entity(world).ok_or(())?;
+            let mut player = world.get_component_mut::<Player>(player_entity).ok_or(())?;
+            player.discovered.insert(self.name.clone());
+        }
+
+        Ok(effects.effects.iter()
+            .map(|e| get_effect_action(e, self.entity))
+            .collect()
+        )
+    }
+    fn score(&self, _world: &World) -> i32 {
+        100
+    }
+}
+
+pub struct Ascend {
+    pub branch: Option<String>
+}
 impl Action for Ascend {
     fn as_any(&self) -> &dyn Any { self }
     fn event(&self) -> GameEvent {
         GameEvent::Ascend
     }
     fn execute(&self, world: &mut World) -> ActionResult {
-        world.get_resource_mut::<Board>().ok_or(())?.exit = true;
+        let mut board = world.get_resource_mut::<Board>().ok_or(())?;
+        board.exit = Some((board.level + 1, self.branch.clone()));
+        Ok(Vec::new())
+    }
+}
+
+pub struct Descend;
+impl Action for Descend {
+    fn as_any(&self) -> &dyn Any { self }
+    fn event(&self) -> GameEvent {
+        GameEvent::Descend
+    }
+    fn execute(&self, world: &mut World) -> ActionResult {
+        let mut board = world.get_resource_mut::<Board>().ok_or(())?;
+        if board.level <= 1 { return Err(()) }
+        board.exit = Some((board.level - 1, board.below.clone()));
         Ok(Vec::new())
     }
 }
 
+pub struct Open {
+    pub entity: Entity,
+    // consumes a key
+    pub is_locked: bool
+}
+impl Action for Open {
+    fn as_any(&self) -> &dyn Any { self }
+    fn event(&self) -> GameEvent {
+        GameEvent::Open(self.is_locked)
+    }
+    fn execute(&self, world: &mut World) -> ActionResult {
+        if self.is_locked {
+            let entity = get_player_entity(world).ok_or(())?;
+            let mut player = world.get_component_mut::<Player>(entity).ok_or(())?;
+            player.keys = player.keys.checked_sub(1).ok_or(())?;
+        }
+        Ok(get_loot_actions(self.entity, world))
+    }
+    // score is not implemented as npcs do not loot containers
+}
+
+fn get_loot_actions(entity: Entity, world: &World) -> Vec<Box<dyn Action>> {
+    // the source can only be replaced once its loot is out
+    let mut actions: Vec<Box<dyn Action>> = vec![Box::new(DropLoot { entity })];
+    if let Some(next) = world.get_component::<Interactive>(entity).and_then(|i| i.next.clone()) {
+        actions.push(Box::new(Replace { entity, name: next }));
+    }
+    actions
+}
+
 pub struct DropLoot {
     pub entity: Entity
 }
 impl Action for DropLoot {
     fn as_any(&self) -> &dyn Any { self }
     fn execute(&self, world: &mut World) -> ActionResult {
-        let mut rng = thread_rng();
+        let mut rng = get_rng();
         let loot = world.get_component::<Loot>(self.entity).ok_or(())?;
         let position = world.get_component::<Position>(self.entity).ok_or(())?.0;
 
         if !rng.gen_bool(loot.chance as f64) { return Ok(Vec::new()) };
 
-        let name = loot.items.choose(&mut rng).ok_or(())?.to_string();
+        let items = loot.items.clone();
         drop(loot);
-        spawn_with_position(world, &name, position);
+        for (i, name) in items.iter().enumerate() {
+            // spread the rest of the drops around the source
+            let v = match i {
+                0 => position,
+                _ => get_empty_neighboring_tile(self.entity, world).unwrap_or(position)
+            };
+            spawn_with_position(world, name, v);
+        }
         Ok(Vec::new())
     }
 }
@@ -964,7 +1787,7 @@ impl Action for Teleport {
             })
             .collect::<Vec<_>>();
 
-        let mut rng = thread_rng();
+        let mut rng = get_rng();
         let target = pool.choose_weighted(&mut rng, |a| a.0).unwrap();
 
         let mut position = world.get_component_mut::<Position>(self.entity).ok_or(())?;
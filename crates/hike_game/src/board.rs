@@ -1,50 +1,155 @@
 use rand::prelude::*;
 use serde::{Deserialize, Serialize};
-use std::collections::{HashMap, HashSet};
+use std::collections::{HashMap, HashSet, VecDeque};
 
-use rogalik::math::vectors::{Vector2i, ORTHO_DIRECTIONS, visible_tiles};
+use rogalik::math::vectors::{Vector2i, ORTHO_DIRECTIONS};
 use::rogalik::storage::{Entity, World};
 
-use hike_data::GameData;
-
-use crate::components::{Position, Player, ViewBlocker, Tile};
-use crate::globals::{BOARD_SIZE, VIEW_RANGE, LEVEL_COUNT};
-use crate::utils::{get_entities_at_position, spawn_with_position};
+use hike_data::{BranchData, GameData, LayoutKind, PrefabData, PREFAB_FLOOR};
+
+use crate::components::{
+    Actor, Effects, Fixture, Interactive, Item, Light, Lockable, Name, Obstacle, Openable, Position, Player, Shop, Tile
+};
+use crate::globals::{
+    BOARD_SIZE, DARK_CHANCE, DARK_MIN_LEVEL, DARK_VIEW_RANGE, DEN_CHANCE, DEN_MIN_AREA,
+    DEN_MIN_LEVEL, DEN_NPC_SHARE, LEVEL_COUNT, LIT_VIEW_RANGE, MAX_GENERATION_TRIES, MAX_TRAPS,
+    PREFAB_CHANCE, SHRINE_ROOM_CHANCE, TRAP_DENSITY, TREASURE_ROOM_CHANCE,
+    TREASURE_ROOM_MIN_LEVEL, VIEW_RANGE
+};
+use crate::layouts::{Layout, Room, RoomKind, get_fallback_layout, get_generator};
+use crate::player::get_worn_items;
+use crate::structs::{Behaviour, EffectKind, InteractionKind, Offer};
+use crate::utils::{field_of_view, get_rng, spawn_with_position};
 
 #[derive(Default, Deserialize, Serialize)]
 pub struct Board {
     pub level: u32,
+    // side path of the tower, None for the main one
+    pub branch: Option<String>,
+    // branch of the floor below
+    pub below: Option<String>,
     pub tiles: HashMap<Vector2i, Entity>,
-    pub exit: bool,
+    // level and branch that the player leaves to
+    pub exit: Option<(u32, Option<String>)>,
     pub player_spawn: Vector2i,
     pub discovered: HashSet<Vector2i>,
     pub visible: HashSet<Vector2i>,
+    pub dark: bool,
+    // light levels of the lit tiles
+    pub light: HashMap<Vector2i, u32>,
+    // room corners
+    pub rooms: Vec<(Vector2i, Vector2i)>,
 }
 impl Board {
-    pub fn new(level: u32) -> Self {
+    pub fn new(level: u32, branch: Option<String>) -> Self {
         Board {
             level,
+            branch,
             ..Default::default()
         }
     }
     pub fn generate(&mut self, world: &mut World) {
+        // regenerate until everything can be reached
+        for _ in 0..MAX_GENERATION_TRIES {
+            self.populate(world, false);
+            if self.is_connected(world) { return }
+            self.clear(world);
+        }
+        // without the set pieces and the pillars only the random fixtures can block the fixed layout
+        self.populate(world, true);
+        if !self.is_connected(world) {
+            remove_blocking_pieces(world);
+        }
+        debug_assert!(self.is_connected(world), "Disconnected fallback floor!");
+    }
+    pub fn is_connected(&self, world: &World) -> bool {
+        // npcs move away and the doors can be opened
+        // the locked doors count as open, as every lock gets its key outside (see add_missing_keys)
+        let query = world.query::<Obstacle>().with::<Position>().build();
+        let blocked = query.entities().zip(query.iter::<Position>())
+            .filter(|(&e, _)| world.get_component::<Actor>(e).is_none()
+                && world.get_component::<Openable>(e).is_none()
+            )
+            .map(|(_, p)| p.0)
+            .collect::<HashSet<_>>();
+        drop(query);
+        let query = world.query::<Interactive>().with::<Position>().build();
+        let interactive = query.iter::<Position>().map(|p| p.0).collect::<HashSet<_>>();
+        drop(query);
+
+        let mut reached = HashSet::from_iter([self.player_spawn]);
+        let mut queue = VecDeque::from([self.player_spawn]);
+        while let Some(v) = queue.pop_front() {
+            for dir in ORTHO_DIRECTIONS.iter() {
+                let n = v + *dir;
+                if !self.tiles.contains_key(&n) || reached.contains(&n) { continue }
+                if blocked.contains(&n) && !interactive.contains(&n) { continue }
+                reached.insert(n);
+                // blocking fixtures are used by bumping, but not walked through
+                if !blocked.contains(&n) { queue.push_back(n) }
+            }
+        }
+
+        let query = world.query::<Position>().build();
+        let result = query.entities().zip(query.iter::<Position>())
+            .filter(|(&e, _)| world.get_component::<Item>(e).is_some()
+                || world.get_component::<Interactive>(e).is_some()
+            )
+            .all(|(_, p)| reached.contains(&p.0));
+        result
+    }
+    fn clear(&mut self, world: &mut World) {
+        let to_remove = world.query::<Position>().build().entities()
+            .copied().collect::<Vec<_>>();
+        for entity in to_remove {
+            world.despawn_entity(entity);
+        }
+        *self = Board::new(self.level, self.branch.clone());
+    }
+    fn populate(&mut self, world: &mut World, fallback: bool) {
         let mut tile_pool = tile_range(
             Vector2i::ZERO,
             Vector2i::new(BOARD_SIZE as i32 - 1, BOARD_SIZE as i32 - 1)
         );
-        for v in tile_pool.iter() {
-            let entity = spawn_with_position(world, "Tile", *v).unwrap();
-            self.tiles.insert(*v, entity);
+        for v in sort_tiles(&tile_pool) {
+            let entity = spawn_with_position(world, "Tile", v).unwrap();
+            self.tiles.insert(v, entity);
         }
 
-        let layout = get_bsp_layout();
-        for v in layout.walls.iter() {
-            let _ = spawn_with_position(world, "Wall", *v);
+        let (boss, layout_kind, dark) = match world.get_resource::<GameData>()
+            .and_then(|d| d.levels.get(&self.level).cloned()) {
+                Some(l) => (l.boss, l.layout, l.dark),
+                None => (None, LayoutKind::Bsp, None)
+            };
+
+        let generator = get_generator(layout_kind);
+        let layout = if fallback { get_fallback_layout() } else { generator.generate() };
+        self.rooms = layout.rooms.iter().map(|r| (r.a, r.b)).collect();
+        for v in sort_tiles(&layout.walls) {
+            let _ = spawn_with_position(world, "Wall", v);
         }
-        let mut rng = thread_rng();
-        for v in layout.doors.iter() {
+        let mut rng = get_rng();
+        self.dark = dark.unwrap_or(
+            self.level >= DARK_MIN_LEVEL && rng.gen_bool(DARK_CHANCE)
+        );
+
+        // like the prefabs, kept out of the boss floors
+        let room_kinds = if boss.is_none() {
+            get_room_kinds(&layout, self.level)
+        } else {
+            HashMap::new()
+        };
+        let treasure = room_kinds.get(&RoomKind::Treasure).map(|&i| &layout.rooms[i]);
+        let treasure_door = treasure.and_then(|r| layout.get_exits(r).into_iter().next());
+        let treasure_tiles = treasure.map(|r| r.tiles());
+
+        for v in sort_tiles(&layout.doors) {
+            if treasure_door == Some(v) {
+                let _ = spawn_with_position(world, "Locked_Door", v);
+                continue
+            }
             if !rng.gen_bool(0.5) { continue };
-            let _ = spawn_with_position(world, "Closed_Door", *v);
+            let _ = spawn_with_position(world, "Closed_Door", v);
         }
 
         // remove walls
@@ -52,15 +157,28 @@ impl Board {
         // remvove doors and adjacent
         tile_pool.retain(|v| !layout.doors.iter().any(|d| d.manhattan(*v) <= 1));
 
-        if self.level > 8 {
-            for v in get_columns(layout.rooms.last().unwrap()) {
+        if self.level > 8 && generator.has_columns() && !fallback {
+            for v in sort_tiles(&get_columns(layout.rooms.last().unwrap())) {
                 tile_pool.remove(&v);
                 let _ = spawn_with_position(world, "Pillar", v);
             }
         }
 
-        if self.level < LEVEL_COUNT {
-            let _ = spawn_with_position(world, "Stair", get_random_tile(&mut tile_pool, None, None).unwrap());
+        // on boss floors the stair appears after the boss is defeated
+        if self.level < LEVEL_COUNT && boss.is_none() {
+            // the way up is never locked
+            let v = get_random_tile(&mut tile_pool, None, treasure_tiles.as_ref()).unwrap();
+            let _ = spawn_with_position(world, "Stair", v);
+            // entrance to a side branch, only from the main tower
+            if let Some(branch) = self.roll_branch(world) {
+                if let Some(v) = get_random_tile(&mut tile_pool, None, treasure_tiles.as_ref()) {
+                    if let Some(entity) = spawn_with_position(world, "Branch_Stair", v) {
+                        if let Some(mut interactive) = world.get_component_mut::<Interactive>(entity) {
+                            interactive.kind = InteractionKind::Branch(branch);
+                        }
+                    }
+                }
+            }
         }
 
         let player_room = &layout.rooms[0].tiles();
@@ -71,6 +189,13 @@ impl Board {
             None
         ).unwrap();
 
+        // leads back to the previous floor
+        if self.level > 1 {
+            if let Some(v) = get_random_tile(&mut tile_pool, Some(player_room), None) {
+                let _ = spawn_with_position(world, "Stair_Down", v);
+            }
+        }
+
         if self.level == 1 {
             let _ = spawn_with_position(
                 world,
@@ -84,28 +209,232 @@ impl Board {
             let _ = spawn_with_position(world, "Second_Book_of_Poetics", v);
         }
 
+        if let Some(boss) = boss {
+            let arena = layout.rooms.last().unwrap().tiles();
+            // the stair only appears after the boss, so it has to be spawned somewhere
+            let v = get_random_tile(&mut tile_pool, Some(&arena), Some(player_room))
+                .or_else(|| get_random_tile(&mut tile_pool, None, Some(player_room)))
+                .or_else(|| get_random_tile(&mut tile_pool, None, None));
+            if let Some(v) = v {
+                let _ = spawn_with_position(world, &boss, v);
+            }
+        }
+
+        if self.dark {
+            for _ in 0..rng.gen_range(1..=2) {
+                let Some(v) = get_random_tile(&mut tile_pool, None, None) else { break };
+                let _ = spawn_with_position(world, "Torch", v);
+            }
+        }
+
+        // set pieces are kept out of the player's room
+        let prefab_score = match boss {
+            None if !fallback => self.place_prefab(world, &layout.rooms[1..], &mut tile_pool),
+            _ => 0
+        };
+        if let Some(room) = treasure {
+            self.fill_treasure_room(world, room, &mut tile_pool);
+        }
+
+        let mut terrain_pool = tile_pool.clone();
+        terrain_pool.retain(|v| !player_room.contains(v));
+        self.place_terrain(world, &terrain_pool);
+
         let pieces = if let Some(data) = world.get_resource::<GameData>() {
-            get_board_pieces(self.level, &data) 
+            let branch = self.branch.as_ref().and_then(|b| data.branches.get(b));
+            let shrine = room_kinds.contains_key(&RoomKind::Shrine);
+            get_board_pieces(self.level, branch, &data, shrine, prefab_score)
         } else { return };
 
+        let den = room_kinds.get(&RoomKind::Den).map(|&i| layout.rooms[i].tiles());
+        let shrine = room_kinds.get(&RoomKind::Shrine).map(|&i| layout.rooms[i].tiles());
         for (name, kind) in pieces {
+            let limit = match kind {
+                PieceKind::Npc if rng.gen_bool(DEN_NPC_SHARE) => den.as_ref(),
+                PieceKind::Fixture => shrine.as_ref(),
+                _ => None
+            };
             let exclude = match kind {
-                PieceKind::Npc => Some(player_room),
+                PieceKind::Npc | PieceKind::Trap => Some(player_room),
+                PieceKind::Container => shrine.as_ref(),
                 _ => None
             };
-            let Some(v) = get_random_tile(
-                &mut tile_pool,
-                None,
-                exclude
-            ) else { continue };
+            // a full room falls back to the whole board
+            let Some(v) = get_random_tile(&mut tile_pool, limit, exclude)
+                .or_else(|| get_random_tile(&mut tile_pool, None, exclude))
+                else { continue };
             let _ = spawn_with_position(world, &name, v);
         }
+        if let Some(tiles) = treasure_tiles.as_ref() {
+            add_missing_keys(world, &mut tile_pool, tiles);
+        }
+        assign_guard_posts(world);
+        fill_shops(world, self.level);
         
         self.tiles.extend(create_bounds(world));
 
     }
     pub fn is_exit(&self) -> bool {
-        self.exit
+        self.exit.is_some()
+    }
+    pub fn get_next_branch(&self, data: &GameData) -> Option<String> {
+        // the main stair keeps the player in the branch until its last floor
+        self.branch.as_ref()
+            .filter(|b| data.branches.get(*b).map_or(false, |d| d.max_level > self.level))
+            .cloned()
+    }
+    fn roll_branch(&self, world: &World) -> Option<String> {
+        if self.branch.is_some() { return None }
+        let data = world.get_resource::<GameData>()?;
+        let mut rng = get_rng();
+        let (name, branch) = data.branches.iter()
+            .filter(|(_, b)| b.min_level == self.level + 1)
+            .choose(&mut rng)?;
+        if !rng.gen_bool(branch.chance) { return None }
+        Some(name.to_string())
+    }
+    fn place_prefab(&mut self, world: &mut World, rooms: &[Room], pool: &mut HashSet<Vector2i>) -> i32 {
+        // returns the score of the spawned npcs
+        let mut rng = get_rng();
+        if !rng.gen_bool(PREFAB_CHANCE) { return 0 }
+
+        let Some(data) = world.get_resource::<GameData>() else { return 0 };
+        let candidates = data.prefabs.values()
+            .filter(|p| p.min_level <= self.level && (p.max_level == 0 || p.max_level >= self.level))
+            .collect::<Vec<_>>();
+        let Ok(prefab) = candidates.choose_weighted(&mut rng, |p| p.spawn_chance.unwrap_or(1.))
+            .map(|p| (*p).clone())
+            else { return 0 };
+        drop(data);
+
+        // every origin at which the prefab fits into a room's free tiles
+        let (w, h) = prefab.dim();
+        let origins = rooms.iter()
+            .flat_map(|r| sort_tiles(&tile_range(r.a, Vector2i::new(r.b.x + 1 - w as i32, r.b.y + 1 - h as i32))))
+            .filter(|o| get_prefab_tiles(&prefab, *o).iter().all(|(v, _)| pool.contains(v)))
+            .collect::<Vec<_>>();
+        let Some(&origin) = origins.choose(&mut rng) else { return 0 };
+
+        let mut score = 0;
+        for (v, c) in get_prefab_tiles(&prefab, origin) {
+            pool.remove(&v);
+            if c == PREFAB_FLOOR { continue }
+            let name = &prefab.legend[&c];
+            let Some(entity) = spawn_with_position(world, name, v) else { continue };
+            if world.get_component::<Actor>(entity).is_some() {
+                score += world.get_resource::<GameData>().map_or(0, |d| d.entities[name].score);
+            }
+            // terrain replaces the base tile
+            if world.get_component::<Tile>(entity).is_some() {
+                if let Some(tile) = self.tiles.insert(v, entity) {
+                    world.despawn_entity(tile);
+                }
+            }
+        }
+        score
+    }
+    fn fill_treasure_room(&self, world: &mut World, room: &Room, pool: &mut HashSet<Vector2i>) {
+        let Some(data) = world.get_resource::<GameData>() else { return };
+        let items = data.roll_loot("Treasure_Room", self.level, &mut get_rng());
+        drop(data);
+
+        let tiles = room.tiles();
+        for item in items {
+            let Some(v) = get_random_tile(pool, Some(&tiles), None) else { break };
+            let _ = spawn_with_position(world, &item, v);
+        }
+    }
+    fn place_terrain(&mut self, world: &mut World, pool: &HashSet<Vector2i>) {
+        let Some(data) = world.get_resource::<GameData>() else { return };
+        let terrain_pool = get_entity_pool(&data, &data.terrain, self.level);
+        drop(data);
+
+        let mut rng = get_rng();
+        for _ in 0..rng.gen_range(0..=2) {
+            let Ok(terrain) = terrain_pool.choose_weighted(&mut rng, |a| a.0) else { break };
+            let Some(&origin) = sort_tiles(pool).choose(&mut rng) else { break };
+            for v in sort_tiles(&get_patch(origin, rng.gen_range(2..=4), pool)) {
+                if let Some(entity) = self.tiles.get(&v) {
+                    world.despawn_entity(*entity);
+                }
+                if let Some(entity) = spawn_with_position(world, &terrain.1, v) {
+                    self.tiles.insert(v, entity);
+                }
+            }
+        }
+    }
+}
+
+pub fn replace_tile(world: &mut World, v: Vector2i, name: &str) {
+    let Some(entity) = world.get_resource::<Board>()
+        .and_then(|b| b.tiles.get(&v).copied()) else { return };
+    world.despawn_entity(entity);
+    let Some(tile) = spawn_with_position(world, name, v) else { return };
+    if let Some(mut board) = world.get_resource_mut::<Board>() {
+        board.tiles.insert(v, tile);
+    }
+}
+
+fn get_prefab_tiles(prefab: &PrefabData, origin: Vector2i) -> Vec<(Vector2i, char)> {
+    // the first row is the top one
+    let h = prefab.map.len() as i32;
+    prefab.map.iter().enumerate()
+        .flat_map(|(y, row)| row.chars().enumerate()
+            .map(move |(x, c)| (origin + Vector2i::new(x as i32, h - 1 - y as i32), c))
+        )
+        .collect()
+}
+
+fn get_patch(origin: Vector2i, size: usize, pool: &HashSet<Vector2i>) -> HashSet<Vector2i> {
+    // random blob grown from the origin
+    let mut rng = get_rng();
+    let mut patch = HashSet::from_iter([origin]);
+    while patch.len() < size {
+        let Some(v) = sort_tiles(&patch).into_iter()
+            .flat_map(|v| ORTHO_DIRECTIONS.iter().map(move |d| v + *d))
+            .filter(|v| pool.contains(v) && !patch.contains(v))
+            .choose(&mut rng)
+            else { break };
+        patch.insert(v);
+    }
+    patch
+}
+
+fn assign_guard_posts(world: &World) {
+    // guards watch over the closest interactive fixture, but not the stairs
+    let query = world.query::<Interactive>().with::<Position>().with::<Fixture>().build();
+    let posts = query.iter::<Interactive>().zip(query.iter::<Position>())
+        .filter(|(i, _)| !matches!(
+            i.kind,
+            InteractionKind::Ascend | InteractionKind::Descend | InteractionKind::Branch(_)
+        ))
+        .map(|(_, p)| p.0)
+        .collect::<Vec<_>>();
+    drop(query);
+    let query = world.query::<Actor>().with::<Position>().build();
+    for (mut actor, position) in query.iter_mut::<Actor>().zip(query.iter::<Position>()) {
+        if actor.behaviour != Behaviour::Guard { continue }
+        actor.home = posts.iter()
+            .min_by_key(|v| v.manhattan(position.0))
+            .map(|&v| v)
+            .or(Some(position.0));
+    }
+}
+
+fn fill_shops(world: &World, level: u32) {
+    let Some(data) = world.get_resource::<GameData>() else { return };
+    let pool = get_entity_pool(&data, &data.items, level).into_iter()
+        .chain(get_entity_pool(&data, &data.weapons, level))
+        .filter(|(_, name)| data.entities[name].price.is_some())
+        .collect::<Vec<_>>();
+
+    let mut rng = get_rng();
+    let query = world.query::<Shop>().build();
+    for mut shop in query.iter_mut::<Shop>() {
+        shop.stock = (0..shop.size)
+            .filter_map(|_| pool.choose_weighted(&mut rng, |a| a.0).ok())
+            .map(|(_, name)| Offer { name: name.to_string(), price: data.entities[name].price.unwrap() })
+            .collect();
     }
 }
 
@@ -147,140 +476,116 @@ fn create_bounds(world: &mut World) -> HashMap<Vector2i, Entity> {
 }
 
 pub fn update_visibility(world: &mut World) {
-    if let Some(position) = world.query::<Player>().with::<Position>().build().single::<Position>() {
-        let Some(mut board) = world.get_resource_mut::<Board>() else { return };
-        let blockers = world.query::<ViewBlocker>().with::<Position>().build().iter::<Position>()
-            .map(|p| p.0)
-            .collect::<HashSet<_>>();
-        let currently_visible = visible_tiles(
-            position.0,
-            &HashSet::from_iter(board.tiles.keys().map(|&v| v)),
-            &blockers,
-            VIEW_RANGE
-        );
-        board.discovered.extend(&currently_visible);
-        board.visible = currently_visible;
+    let Some(entity) = world.query::<Player>().with::<Position>().build().single_entity() else { return };
+    let Some(position) = world.get_component::<Position>(entity).map(|p| p.0) else { return };
+    let Some(dark) = world.get_resource::<Board>().map(|b| b.dark) else { return };
+
+    // the player carries the light of the worn items
+    let player_light = get_worn_items(world, entity).iter()
+        .filter_map(|&e| world.get_component::<Light>(e).map(|l| l.0))
+        .max()
+        .unwrap_or(0);
+    let query = world.query::<Light>().with::<Position>().build();
+    let mut sources = query.iter::<Light>().zip(query.iter::<Position>())
+        .map(|(l, p)| (p.0, l.0))
+        .collect::<Vec<_>>();
+    sources.push((position, player_light));
+    let light = get_light_levels(world, &sources);
+
+    let range = if dark { DARK_VIEW_RANGE } else { VIEW_RANGE };
+    let mut currently_visible = field_of_view(world, position, range.max(player_light));
+    // lit tiles are seen from further away
+    currently_visible.extend(
+        field_of_view(world, position, LIT_VIEW_RANGE).into_iter()
+            .filter(|v| light.contains_key(v))
+    );
+
+    let Some(mut board) = world.get_resource_mut::<Board>() else { return };
+    board.discovered.extend(&currently_visible);
+    board.visible = currently_visible;
+    board.light = light;
+}
+
+fn get_light_levels(world: &World, sources: &[(Vector2i, u32)]) -> HashMap<Vector2i, u32> {
+    // the level fades with the distance from the source
+    let mut light = HashMap::new();
+    for &(origin, radius) in sources {
+        if radius == 0 { continue }
+        for v in field_of_view(world, origin, radius) {
+            let level = (radius + 1).saturating_sub(v.manhattan(origin) as u32);
+            if level == 0 { continue }
+            let current = light.entry(v).or_insert(0);
+            *current = level.max(*current);
+        }
     }
+    light
 }
 
 enum PieceKind {
     Npc,
     Item,
-    Fixture
+    Fixture,
+    // spawned like the fixtures, but kept out of the shrine room
+    Container,
+    Trap
 }
 
-struct Layout {
-    doors: HashSet<Vector2i>,
-    walls: HashSet<Vector2i>,
-    rooms: Vec<Room>
+pub fn tile_range(a: Vector2i, b: Vector2i) -> HashSet<Vector2i> {
+    (a.x..=b.x).map(
+            |x| (a.y..=b.y).map(move |y| Vector2i::new(x, y))
+        )
+        .flatten()
+        .collect()
 }
 
-struct Room {
-    pub a: Vector2i,
-    pub b: Vector2i,
-    pub doors: Vec<Vector2i>
-}
-impl Room {
-    pub fn tiles(&self) -> HashSet<Vector2i> {
-        tile_range(self.a, self.b)
-    }
-    pub fn area(&self) -> u32 {
-        let (w, h) = self.dim();
-        w * h
-    }
-    pub fn dim(&self) -> (u32, u32) {
-        // ((self.b.x - self.a.x).abs() as u32, (self.b.y - self.a.y).abs() as u32)
-        ((self.b.x - self.a.x) as u32 + 1, (self.b.y - self.a.y) as u32 + 1)
-    }
+pub fn sort_tiles<'a>(tiles: impl IntoIterator<Item = &'a Vector2i>) -> Vec<Vector2i> {
+    // hash sets iterate in a random order, which would break the seeded generation
+    let mut tiles = tiles.into_iter().copied().collect::<Vec<_>>();
+    tiles.sort_by_key(|v| (v.y, v.x));
+    tiles
 }
 
-fn get_bsp_layout() -> Layout {
-    'outer: loop {
-        let base = Room {
-            a: Vector2i::ZERO,
-            b: Vector2i::new(BOARD_SIZE as i32 - 1, BOARD_SIZE as i32 - 1),
-            doors: Vec::new()
-        };
-        let mut wall_tiles = base.tiles();
-        let mut rooms = divide_room(base);
-        if rooms.len() < 3 { continue; }
-        let mut doors = HashSet::new();
-        for r in rooms.iter() {
-            doors.extend(&r.doors);
-            for v in r.tiles() {
-                wall_tiles.remove(&v);
-            }
-            for v in r.doors.iter() {
-                wall_tiles.remove(&v);
-            }
-        }
-
-        // extra door validation for safety
-        for door in doors.iter() {
-            let n = ORTHO_DIRECTIONS.iter()
-                .filter(|dir| wall_tiles.contains(&(*door + **dir)))
-                .count();
-            if n > 2 { continue 'outer }
-        }
-
-        rooms.sort_by(|a, b| a.area().cmp(&b.area()));
-
-        return Layout {
-            walls: wall_tiles,
-            doors,
-            rooms
-        };
+fn remove_blocking_pieces(world: &mut World) {
+    // the fallback layout is connected by itself, so only the walls are kept as obstacles
+    let query = world.query::<Obstacle>().with::<Position>().build();
+    let to_remove = query.entities()
+        .filter(|&&e| world.get_component::<Actor>(e).is_none()
+            && world.get_component::<Openable>(e).is_none()
+            && world.get_component::<Name>(e).map_or(true, |n| n.0 != "Wall")
+        )
+        .copied()
+        .collect::<Vec<_>>();
+    drop(query);
+    for entity in to_remove {
+        world.despawn_entity(entity);
     }
 }
 
-fn divide_room(r: Room) -> Vec<Room> {
-    let dx = r.b.x - r.a.x;
-    let dy = r.b.y - r.a.y;
-    if  dx < 4 && dy < 4 { return vec![r] }
-    let vertical = dx < dy;
-    let mut rng = thread_rng();
-
-    let split_val = if vertical { rng.gen_range(r.a.y + 2..r.b.y -1 ) }
-        else { rng.gen_range(r.a.x + 2..r.b.x -1 ) };
-
-    // check existing door collision
-    if vertical && r.doors.iter().any(|&v| v.x == split_val) { return vec![r] }
-        else {
-            if r.doors.iter().any(|&v| v.y == split_val) { return vec![r] }
+fn add_missing_keys(world: &mut World, pool: &mut HashSet<Vector2i>, locked: &HashSet<Vector2i>) {
+    // keys open any lock, so each lock on the floor needs one outside of the treasure room
+    // this is what lets is_connected treat the locked doors as open
+    // the barred doors are only paid for with gold and guard the optional vaults,
+    // never the stairs, so they are treated as open as well
+    let query = world.query::<Position>().build();
+    let (mut locks, mut keys) = (0, 0);
+    for (&e, p) in query.entities().zip(query.iter::<Position>()) {
+        locks += world.get_component::<Lockable>(e).map_or(0, |l| l.keys);
+        if world.get_component::<Interactive>(e).is_some_and(|i| i.kind == InteractionKind::Unlock) {
+            locks += 1;
         }
-
-    let corner_a = if vertical { Vector2i::new(r.b.x, split_val - 1) } else { Vector2i::new(split_val - 1, r.b.y) };
-    let corner_b = if vertical { Vector2i::new(r.a.x, split_val + 1) } else { Vector2i::new(split_val + 1, r.a.y) };
-
-    let mut doors = r.doors.clone();
-    let door = get_bsp_door(vertical, split_val, r.a, r.b);
-    
-    // consider extra door for large rooms
-    if dx.max(dy) > 5 && rng.gen_bool(0.75) {
-        let extra_door = get_bsp_door(vertical, split_val, r.a, r.b);
-        if extra_door.manhattan(door) > 1 { doors.push(extra_door) };
+        if locked.contains(&p.0) || world.get_component::<Item>(e).is_none() { continue }
+        keys += world.get_component::<Effects>(e).map_or(0, |e| e.effects.iter()
+            .filter(|a| matches!(a.kind, EffectKind::Key))
+            .map(|a| a.value)
+            .sum()
+        );
     }
-    doors.push(door);
-
-    let room_a = Room { a: r.a, b: corner_a, doors: doors.clone() };
-    let room_b = Room { a: corner_b, b: r.b, doors };
-    let mut res = divide_room(room_a);
-    res.extend(divide_room(room_b));
-    res
-}
+    drop(query);
 
-fn get_bsp_door(vertical: bool, split_val: i32, a: Vector2i, b: Vector2i) -> Vector2i {
-    let mut rng = thread_rng();
-    if vertical { Vector2i::new(rng.gen_range(a.x..=b.x), split_val) }
-        else { Vector2i::new(split_val, rng.gen_range(a.y..=b.y))}
-}
-
-fn tile_range(a: Vector2i, b: Vector2i) -> HashSet<Vector2i> {
-    (a.x..=b.x).map(
-            |x| (a.y..=b.y).map(move |y| Vector2i::new(x, y))
-        )
-        .flatten()
-        .collect()
+    for _ in keys..locks {
+        let Some(v) = get_random_tile(pool, None, Some(locked)) else { break };
+        let _ = spawn_with_position(world, "Key", v);
+    }
 }
 
 fn get_random_tile(
@@ -288,7 +593,7 @@ fn get_random_tile(
     limit: Option<&HashSet<Vector2i>>,
     exclude: Option<&HashSet<Vector2i>>,
 ) -> Option<Vector2i> {
-    let mut rng = thread_rng();
+    let mut rng = get_rng();
     let mut target_pool = pool.clone();
     if let Some(limit) = limit {
         target_pool.retain(|v| limit.contains(v));
@@ -297,7 +602,7 @@ fn get_random_tile(
         target_pool.retain(|v| !exclude.contains(v));
     }
 
-    let v = *target_pool.iter().choose(&mut rng)?;
+    let v = *sort_tiles(&target_pool).choose(&mut rng)?;
     pool.remove(&v);
     Some(v)
 }
@@ -317,32 +622,96 @@ fn get_entity_pool<'a>(data: &'a GameData, base: &'a Vec<String>, level: u32) ->
         .collect()
 }
 
-fn get_board_pieces(level: u32, data: &GameData) -> Vec<(String, PieceKind)> {
-    let target_score = get_target_score(level);
-    let mut rng = thread_rng();
+fn get_branch_pool(
+    data: &GameData,
+    branch: Option<&Vec<String>>,
+    base: &Vec<String>,
+    level: u32
+) -> Vec<(f32, String)> {
+    // fall back to the default pool when nothing from the branch fits the level
+    let pool = branch.map(|b| get_entity_pool(data, b, level)).unwrap_or_default();
+    if !pool.is_empty() { return pool }
+    get_entity_pool(data, base, level)
+}
+
+fn get_room_kinds(layout: &Layout, level: u32) -> HashMap<RoomKind, usize> {
+    // the player's room is never special
+    let mut rng = get_rng();
+    let mut kinds = HashMap::new();
+    let mut candidates = (1..layout.rooms.len()).collect::<Vec<_>>();
+
+    // a dead end, so that the locked door blocks nothing else
+    if level >= TREASURE_ROOM_MIN_LEVEL && rng.gen_bool(TREASURE_ROOM_CHANCE) {
+        let dead_ends = candidates.iter()
+            .filter(|&&i| {
+                let exits = layout.get_exits(&layout.rooms[i]);
+                exits.len() == 1 && exits.iter().all(|v| layout.doors.contains(v))
+            })
+            .copied()
+            .collect::<Vec<_>>();
+        if let Some(&i) = dead_ends.choose(&mut rng) {
+            kinds.insert(RoomKind::Treasure, i);
+            candidates.retain(|&c| c != i);
+        }
+    }
+
+    if level >= DEN_MIN_LEVEL && rng.gen_bool(DEN_CHANCE) {
+        let largest = candidates.iter()
+            .filter(|&&i| layout.rooms[i].area() >= DEN_MIN_AREA)
+            .max_by_key(|&&i| layout.rooms[i].area())
+            .copied();
+        if let Some(i) = largest {
+            kinds.insert(RoomKind::Den, i);
+            candidates.retain(|&c| c != i);
+        }
+    }
+
+    if rng.gen_bool(SHRINE_ROOM_CHANCE) {
+        if let Some(&i) = candidates.choose(&mut rng) {
+            kinds.insert(RoomKind::Shrine, i);
+        }
+    }
+    kinds
+}
 
-    let weapon_count: usize = rng.gen_range(0..=1) + (level + 1) as usize % 2;
+fn get_board_pieces(
+    level: u32,
+    branch: Option<&BranchData>,
+    data: &GameData,
+    shrine: bool,
+    prefab_score: i32
+) -> Vec<(String, PieceKind)> {
+    let target_score = get_target_score(level) + branch.map_or(0, |b| b.difficulty);
+    let mut rng = get_rng();
+
+    let weapon_count: usize = rng.gen_range(0..=1) + (level + 1) as usize % 2
+        + branch.map_or(0, |b| b.extra_weapons as usize);
     let item_count: usize = rng.gen_range(1..=2);
 
     let (mut items, mut npcs, mut fixtures) = match data.levels.get(&level) {
         Some(l) => (l.required_items.clone(), l.required_npcs.clone(), l.required_fixtures.clone()),
         None => (Vec::new(), Vec::new(), Vec::new())
     };
-    let item_pool = get_entity_pool(data, &data.items, level);
+    let item_pool = get_branch_pool(data, branch.map(|b| &b.items), &data.items, level);
     for _ in 0..item_count.saturating_sub(items.len()) {
         items.push(item_pool.choose_weighted(&mut rng, |a| a.0).unwrap().1.clone());
     }
 
-    let weapon_pool = get_entity_pool(data, &data.weapons, level);
+    let weapon_pool = get_branch_pool(data, branch.map(|b| &b.weapons), &data.weapons, level);
     for _ in 0..weapon_count {
         items.push(weapon_pool.choose_weighted(&mut rng, |a| a.0).unwrap().1.clone());
     }
 
-    let mut npc_score: i32 = npcs.iter()
+    // npcs from the set pieces count as well
+    let mut npc_score: i32 = prefab_score + npcs.iter()
         .map(|n| data.entities[n].score)
-        .sum();
+        .sum::<i32>();
+    // bosses take up the floor's difficulty budget
+    if let Some(boss) = data.levels.get(&level).and_then(|l| l.boss.as_ref()) {
+        npc_score += data.entities[boss].score;
+    }
 
-    let npc_pool = get_entity_pool(data, &data.npcs, level);
+    let npc_pool = get_branch_pool(data, branch.map(|b| &b.npcs), &data.npcs, level);
     while npc_score < target_score {
         let npc = npc_pool.choose_weighted(&mut rng, |a|
                 // take already added npcs into account
@@ -358,21 +727,37 @@ fn get_board_pieces(level: u32, data: &GameData) -> Vec<(String, PieceKind)> {
         npcs.iter().map(|a| (a.clone(), PieceKind::Npc))
     );
 
-    // TODO redo
-    if fixtures.len() == 0 && level % 2 == 0 && level > 2 {
+    // a shrine room always holds a fixture
+    if fixtures.len() == 0 && (shrine || level % 2 == 0 && level > 2) {
         let pool = get_entity_pool(&data, &data.fixtures, level);
         fixtures.push(pool.choose_weighted(&mut rng, |a| a.0).unwrap().1.clone())
     }
 
+    // containers
+    let container_pool = get_entity_pool(&data, &data.containers, level);
+    let container_count: usize = rng.gen_range(0..=2);
+    let mut containers = Vec::new();
+    for _ in 0..container_count {
+        let Ok(container) = container_pool.choose_weighted(&mut rng, |a| a.0) else { break };
+        containers.push(container.1.clone());
+    }
+
     // traps
-    // let trap_pool = get_entity_pool(&data, &data.traps, level);
-    // let trap_count = rng.gen_range(0..6);
-    // for _ in 0..trap_count {
-    //     output.push(trap_pool.choose_weighted(&mut rng, |a| a.0).unwrap().1.clone());
-    // }
+    let trap_pool = get_entity_pool(&data, &data.traps, level);
+    let trap_count = match data.levels.get(&level).and_then(|l| l.traps) {
+        Some(count) => count as usize,
+        None => ((level as f32 * TRAP_DENSITY) as usize).min(MAX_TRAPS)
+    };
+    for _ in 0..trap_count {
+        let Ok(trap) = trap_pool.choose_weighted(&mut rng, |a| a.0) else { break };
+        output.push((trap.1.clone(), PieceKind::Trap));
+    }
 
     output.extend(
         fixtures.iter().map(|a| (a.clone(), PieceKind::Fixture))
     );
+    output.extend(
+        containers.iter().map(|a| (a.clone(), PieceKind::Container))
+    );
     output
 }
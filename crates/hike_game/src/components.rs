@@ -4,8 +4,11 @@ use serde::{Serialize, Deserialize, Deserializer};
 use std::collections::{HashSet, HashMap};
 
 use crate::actions::Action;
-use crate::globals::MAX_WEAPONS;
-use crate::structs::{Attack, Attitude, Effect, InteractionKind, ValueMax};
+use crate::globals::{EQUIPMENT_SLOTS, MAX_WEAPONS, VIEW_RANGE};
+use crate::structs::{
+    Attack, AttackKind, Attitude, Behaviour, BlessingKind, Effect, EquipmentSlot, HazardKind, InteractionKind,
+    Offer, Phase, TrapEffect, ValueMax
+};
 use crate::utils::{deserialize_random_u32, deserialize_none, serialize_as_none};
 
 
@@ -15,9 +18,32 @@ pub struct Actor {
     #[serde(default)]
     pub target: Option<Vector2i>,
     #[serde(default)]
-    pub attitude: Attitude
+    pub attitude: Attitude,
+    #[serde(default)]
+    pub behaviour: Behaviour,
+    // guarded position
+    #[serde(default)]
+    pub home: Option<Vector2i>,
+    #[serde(default="default_view_range")]
+    pub view_range: u32,
+    // turns left before the last seen player position is forgotten
+    #[serde(default)]
+    pub memory: u32
 }
 impl Component for Actor {}
+fn default_view_range() -> u32 { VIEW_RANGE }
+
+#[derive(Serialize, Deserialize)]
+// reduces the hit damage taken by the wearer
+pub struct Armor(pub u32);
+impl Component for Armor {}
+
+#[derive(Serialize, Deserialize)]
+pub struct Boss {
+    pub title: String,
+    pub phase: Option<Phase>
+}
+impl Component for Boss {}
 
 #[derive(Serialize, Deserialize)]
 pub struct Budding;
@@ -53,6 +79,13 @@ pub struct Effects {
 }
 impl Component for Effects {}
 
+// item worn in one of the player's equipment slots
+#[derive(Serialize, Deserialize)]
+pub struct Equipment {
+    pub slot: EquipmentSlot
+}
+impl Component for Equipment {}
+
 #[derive(Serialize, Deserialize)]
 // fixed tile furnishings
 pub struct Fixture;
@@ -62,6 +95,15 @@ impl Component for Fixture {}
 pub struct Health(pub ValueMax);
 impl Component for Health {}
 
+// spreads and fades out each turn
+#[derive(Serialize, Deserialize)]
+pub struct Hazard {
+    pub kind: HazardKind,
+    // turns left
+    pub strength: u32
+}
+impl Component for Hazard {}
+
 // marker component for items used automatically upon walking on them
 #[derive(Serialize, Deserialize)]
 pub struct Instant;
@@ -96,13 +138,48 @@ pub struct Info {
 }
 impl Component for Info {}
 
+// radius of the lit area around the source (or the wearer)
+#[derive(Serialize, Deserialize)]
+pub struct Light(pub u32);
+impl Component for Light {}
+
+// requires the player's keys to be opened
+#[derive(Serialize, Deserialize)]
+pub struct Lockable {
+    pub keys: u32
+}
+impl Component for Lockable {}
+
 #[derive(Serialize, Deserialize)]
 pub struct Loot {
+    // named loot table, rolled when the entity is spawned
+    pub table: String,
+    #[serde(default)]
     pub items: Vec<String>,
     pub chance: f32
 }
 impl Component for Loot {}
 
+// transforms into the next entity when bumped into (eg. doors)
+#[derive(Serialize, Deserialize)]
+pub struct Openable {
+    pub next: String,
+    pub cost: Option<u32>
+}
+impl Component for Openable {}
+
+#[derive(Serialize, Deserialize)]
+// heals the wearer each time the cooldown runs out
+pub struct Mending {
+    pub cooldown: ValueMax
+}
+impl Component for Mending {}
+
+#[derive(Serialize, Deserialize)]
+// volume of the sound made when destroyed or replaced
+pub struct Noise(pub u32);
+impl Component for Noise {}
+
 #[derive(Serialize, Deserialize)]
 // actor cannot travel to a blocked tile
 pub struct Obstacle;
@@ -122,6 +199,21 @@ pub struct Ranged {
 }
 impl Component for Ranged {}
 
+#[derive(Serialize, Deserialize)]
+// attack kinds that have no effect on the wearer
+pub struct Resistance(pub Vec<AttackKind>);
+impl Component for Resistance {}
+
+#[derive(Serialize, Deserialize)]
+// merchant stock - filled on board generation
+pub struct Shop {
+    #[serde(deserialize_with="deserialize_random_u32")]
+    pub size: u32,
+    #[serde(default)]
+    pub stock: Vec<Offer>
+}
+impl Component for Shop {}
+
 #[derive(Serialize, Deserialize)]
 pub struct Summoner {
     pub creature: String,
@@ -133,6 +225,38 @@ impl Component for Summoner {}
 pub struct Tile;
 impl Component for Tile {}
 
+// floor properties, affecting entities that stand on the tile
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct Terrain {
+    // entering the tile takes an extra turn
+    #[serde(default)]
+    pub slowing: bool,
+    // lunges cannot be performed from the tile
+    #[serde(default)]
+    pub sticky: bool,
+    // dealt at the end of each turn
+    #[serde(default)]
+    pub damage: u32,
+    // puts out the fire
+    #[serde(default)]
+    pub extinguishing: bool,
+    // sets the neighbouring tiles on fire
+    #[serde(default)]
+    pub igniting: bool,
+    // flammable terrain turns into this tile once burnt
+    pub burnt: Option<String>,
+    // the tile turns into another one when walked on
+    pub trampled: Option<String>
+}
+impl Component for Terrain {}
+
+// pressure plate triggered by entities stepping on it
+#[derive(Serialize, Deserialize)]
+pub struct Trap {
+    pub effects: Vec<TrapEffect>
+}
+impl Component for Trap {}
+
 #[derive(Serialize, Deserialize)]
 pub struct Transition {
     pub next: String
@@ -147,6 +271,21 @@ impl Component for Weapon {}
 pub struct Immaterial;
 impl Component for Immaterial {}
 
+// not rendered until found (eg. traps)
+#[derive(Serialize, Deserialize)]
+pub struct Hidden;
+impl Component for Hidden {}
+
+// destroyed by the fire
+#[derive(Serialize, Deserialize)]
+pub struct Flammable;
+impl Component for Flammable {}
+
+// npcs that can open unlocked doors
+#[derive(Serialize, Deserialize)]
+pub struct Opener;
+impl Component for Opener {}
+
 #[derive(Serialize, Deserialize)]
 pub struct Lunge;
 impl Component for Lunge {
@@ -189,16 +328,40 @@ impl Component for ViewBlocker {}
 pub struct Name (pub String);
 impl Component for Name {}
 
+// weapon modifiers rolled on spawn
+#[derive(Default, Serialize, Deserialize)]
+pub struct Affixes {
+    pub prefix: Option<String>,
+    pub suffix: Option<String>
+}
+impl Component for Affixes {}
+
+// number of identical collectables held in a single inventory slot
+#[derive(Serialize, Deserialize)]
+pub struct Stack(pub u32);
+impl Component for Stack {}
+
+// rolled on spawn, hidden until identified or equipped
+#[derive(Serialize, Deserialize)]
+pub struct Blessing {
+    pub kind: BlessingKind,
+    pub known: bool
+}
+impl Component for Blessing {}
+
 #[derive(Default, Serialize, Deserialize)]
 pub struct Player {
     #[serde(deserialize_with="deserialize_none")]
     #[serde(serialize_with="serialize_as_none")]
     pub action: Option<Box<dyn Action>>,
     pub weapons: [Option<Entity>; MAX_WEAPONS],
+    // indexed by the EquipmentSlot
+    pub equipment: [Option<Entity>; EQUIPMENT_SLOTS],
     pub discovered: HashSet<String>,
     pub collectables: Vec<Entity>,
     pub active_weapon: usize,
     pub gold: u32,
+    pub keys: u32,
 }
 impl Component for Player {}
 
@@ -214,6 +377,11 @@ impl Component for Stunned {}
 pub struct Poisoned(pub u32);
 impl Component for Poisoned {}
 
+// loses the next turn, after wading through the terrain
+#[derive(Serialize, Deserialize)]
+pub struct Slowed;
+impl Component for Slowed {}
+
 #[derive(Serialize, Deserialize)]
 pub struct Projectile {
     pub attacks: Vec<Attack>,
@@ -230,47 +398,66 @@ impl Component for Position {}
 pub struct Regeneration(pub u32);
 impl Component for Regeneration {}
 
-pub fn insert_data_components(
-    entity: Entity,
-    world: &mut World,
-    value: &serde_yaml::Value
-) {
-    let Some(data) = value.as_mapping() else { return };
-    for (name, component_data) in data.iter() {
-        let Some(name) = name.as_str() else { continue };
-        match name {
-            "Actor" => insert_single::<Actor>(entity, world, component_data),
-            "Budding" => insert_single::<Budding>(entity, world, component_data),
-            "Collectable" => insert_single::<Collectable>(entity, world, component_data),
-            "Defensive" => insert_single::<Defensive>(entity, world, component_data),
-            "Discoverable" => insert_single::<Discoverable>(entity, world, component_data),
-            "Durability" => insert_single::<Durability>(entity, world, component_data),
-            "Effects" => insert_single::<Effects>(entity, world, component_data),
-            "Fixture" => insert_single::<Fixture>(entity, world, component_data),
-            "Health" => insert_single::<Health>(entity, world, component_data),
-            "Immaterial" => insert_single::<Immaterial>(entity, world, component_data),
-            "Interactive" => insert_single::<Interactive>(entity, world, component_data),
-            "Instant" => insert_single::<Instant>(entity, world, component_data),
-            "Item" => insert_single::<Item>(entity, world, component_data),
-            "Info" => insert_single::<Info>(entity, world, component_data),
-            "Loot" => insert_single::<Loot>(entity, world, component_data),
-            "Lunge" => insert_single::<Lunge>(entity, world, component_data),
-            "Swing" => insert_single::<Swing>(entity, world, component_data),
-            "Obstacle" => insert_single::<Obstacle>(entity, world, component_data),
-            "Offensive" => insert_single::<Offensive>(entity, world, component_data),
-            "Ranged" => insert_single::<Ranged>(entity, world, component_data),
-            "Summoner" => insert_single::<Summoner>(entity, world, component_data),
-            "Push" => insert_single::<Push>(entity, world, component_data),
-            "Switch" => insert_single::<Switch>(entity, world, component_data),
-            "Tile" => insert_single::<Tile>(entity, world, component_data),
-            "Transition" => insert_single::<Transition>(entity, world, component_data),
-            "Weapon" => insert_single::<Weapon>(entity, world, component_data),
-            "ViewBlocker" => insert_single::<ViewBlocker>(entity, world, component_data),
-            a => panic!("Unknown component {a}")
-        };
-    }
+#[derive(Serialize, Deserialize)]
+pub struct Stealthy(pub u32);
+impl Component for Stealthy {}
+
+// single registry of the components, used by the data files and the saves
+macro_rules! data_components {
+    ($($component:ident),* $(,)?) => {
+        pub fn insert_data_components(
+            entity: Entity,
+            world: &mut World,
+            value: &serde_yaml::Value
+        ) {
+            let Some(data) = value.as_mapping() else { return };
+            for (name, component_data) in data.iter() {
+                let Some(name) = name.as_str() else { continue };
+                match name {
+                    $(stringify!($component) => insert_single::<$component>(entity, world, component_data),)*
+                    a => panic!("Unknown component {a}")
+                };
+            }
+        }
+
+        pub fn try_insert_data_components(
+            entity: Entity,
+            world: &mut World,
+            value: &serde_yaml::Value
+        ) -> Result<(), ()> {
+            // as above, but for the stored data that can not be trusted
+            let data = value.as_mapping().ok_or(())?;
+            for (name, component_data) in data.iter() {
+                match name.as_str().ok_or(())? {
+                    $(stringify!($component) => try_insert_single::<$component>(entity, world, component_data)?,)*
+                    _ => return Err(())
+                };
+            }
+            Ok(())
+        }
+
+        pub fn get_data_components(entity: Entity, world: &World) -> serde_yaml::Mapping {
+            // inverse of the insert_data_components
+            let mut data = serde_yaml::Mapping::new();
+            $(get_single::<$component>(entity, world, stringify!($component), &mut data);)*
+            data
+        }
+
+        pub fn register_serializable_components(world: &mut World) {
+            $(world.register_serializable_component::<$component>(stringify!($component));)*
+        }
+    };
 }
 
+data_components!(
+    Actor, Armor, Boss, Budding, Collectable, Defensive, Discoverable, Durability, Effects,
+    Equipment, Fixture, Flammable, Hazard, Health, Hidden, Immaterial, Interactive, Instant, Item,
+    Info, Light, Lockable, Loot, Lunge, Mending, Noise, Swing, Obstacle, Offensive, Openable,
+    Opener, Ranged, Resistance, Shop, Summoner, Push, Switch, Terrain, Tile, Transition, Trap,
+    Weapon, ViewBlocker, Affixes, Blessing, Immune, Name, Player, Poisoned, Position, Projectile,
+    Regeneration, Slowed, Stack, Stealthy, Stunned
+);
+
 fn insert_single<T>(
     entity: Entity,
     world: &mut World,
@@ -279,3 +466,24 @@ fn insert_single<T>(
     let component = serde_yaml::from_value::<T>(data.clone()).expect(&format!("Could not parse {:?}", data));
     let _ =world.insert_component(entity, component);
 }
+
+fn try_insert_single<T>(
+    entity: Entity,
+    world: &mut World,
+    data: &serde_yaml::Value
+) -> Result<(), ()> where for<'de> T: 'static + Component + Deserialize<'de> {
+    let component = serde_yaml::from_value::<T>(data.clone()).map_err(|_| ())?;
+    let _ = world.insert_component(entity, component);
+    Ok(())
+}
+
+fn get_single<T>(
+    entity: Entity,
+    world: &World,
+    name: &str,
+    data: &mut serde_yaml::Mapping
+) where T: 'static + Component + Serialize {
+    let Some(component) = world.get_component::<T>(entity) else { return };
+    let Ok(value) = serde_yaml::to_value(&*component) else { return };
+    data.insert(name.into(), value);
+}
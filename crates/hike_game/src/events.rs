@@ -13,14 +13,27 @@ pub enum GameEvent {
     HealPoison(Entity),
     Immunity(Entity),
     Regeneration(Entity),
+    Stealth(Entity),
     Poison(Entity, i32),
     Attack(Entity, Vector2i),
+    BossPhase(Vector2i),
     HitProjectile(Vector2i),
     Travel(Entity, bool), // bool: is_animated,
     Ascend,
+    Descend,
     PickItem,
     UseCollectable,
     Upgrade,
+    Enchant(Entity, u32),
+    Identify(Entity),
+    Reveal(Entity),
+    Gamble(Entity, bool), // bool: is_win
+    Sacrifice(Entity, u32),
+    Quaff(Entity, bool), // bool: is_seen
+    Open(bool), // bool: is_locked
+    Refused(Entity), // missing keys or gold
+    TriggerTrap(Entity),
+    FindTrap(Entity),
     Spawn,
     Win,
     Defeat
@@ -0,0 +1,143 @@
+use rogalik::{
+    math::vectors::{Vector2i, ORTHO_DIRECTIONS},
+    storage::World
+};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashSet, VecDeque};
+
+use crate::board::Board;
+use crate::components::{
+    Actor, Interactive, Name, Obstacle, Position, Projectile, Tile, get_data_components, try_insert_data_components
+};
+use crate::structs::InteractionKind;
+use crate::utils::get_entities_at_position;
+
+// left floors, by the level and the branch
+// kept as a list, as not every save format accepts tuple keys
+#[derive(Default, Deserialize, Serialize)]
+pub struct Floors(pub Vec<((u32, Option<String>), Floor)>);
+
+#[derive(Deserialize, Serialize)]
+pub struct Floor {
+    pub board: Board,
+    pub entities: Vec<StoredEntity>
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct StoredEntity {
+    pub name: String,
+    // yaml encoded, read back as the data components
+    pub components: String
+}
+
+pub fn store_floor(world: &mut World) {
+    // the entities are frozen until the player comes back
+    let query = world.query::<Name>().with::<Position>().build();
+    let entities = query.entities().zip(query.iter::<Name>())
+        .filter(|(&e, _)| world.get_component::<Projectile>(e).is_none())
+        .filter_map(|(&e, name)| Some(StoredEntity {
+            name: name.0.clone(),
+            components: serde_yaml::to_string(&get_data_components(e, world)).ok()?
+        }))
+        .collect::<Vec<_>>();
+    drop(query);
+
+    let Some(board) = world.get_resource_mut::<Board>()
+        .map(|mut b| {
+            let (level, branch) = (b.level, b.branch.clone());
+            std::mem::replace(&mut *b, Board::new(level, branch))
+        })
+        else { return };
+    let key = (board.level, board.branch.clone());
+
+    if world.get_resource::<Floors>().is_none() {
+        world.insert_resource(Floors::default());
+    }
+    let Some(mut floors) = world.get_resource_mut::<Floors>() else { return };
+    floors.0.retain(|(k, _)| *k != key);
+    floors.0.push((key, Floor { board, entities }));
+}
+
+pub fn restore_floor(world: &mut World, level: u32, branch: &Option<String>) -> Result<bool, ()> {
+    // Ok(false) when the floor has not been visited yet,
+    // Err when the stored floor could not be read back
+    let Some(floor) = world.get_resource_mut::<Floors>()
+        .and_then(|mut f| {
+            let idx = f.0.iter().position(|(k, _)| k.0 == level && k.1 == *branch)?;
+            Some(f.0.remove(idx).1)
+        })
+        else { return Ok(false) };
+    let (previous, from_branch) = world.get_resource::<Board>()
+        .map_or((0, None), |b| (b.level, b.branch.clone()));
+
+    let mut spawned = Vec::new();
+    for stored in floor.entities {
+        let entity = world.spawn_entity();
+        spawned.push(entity);
+        let _ = world.insert_component(entity, Name(stored.name));
+        let result = serde_yaml::from_str::<serde_yaml::Value>(&stored.components)
+            .map_err(|_| ())
+            .and_then(|data| try_insert_data_components(entity, world, &data));
+        if result.is_err() {
+            // do not leave a half restored floor behind
+            for entity in spawned {
+                world.despawn_entity(entity);
+            }
+            return Err(())
+        }
+    }
+
+    let mut board = floor.board;
+    board.exit = None;
+    // tiles are respawned with new ids
+    let query = world.query::<Tile>().with::<Position>().build();
+    board.tiles = query.entities().zip(query.iter::<Position>())
+        .map(|(&e, p)| (p.0, e))
+        .collect();
+    drop(query);
+
+    let stair = if previous < level {
+        InteractionKind::Descend
+    } else {
+        match from_branch {
+            Some(b) if Some(&b) != branch.as_ref() => InteractionKind::Branch(b),
+            _ => InteractionKind::Ascend
+        }
+    };
+    if let Some(v) = get_arrival_tile(world, &board, stair) {
+        board.player_spawn = v;
+    }
+    world.insert_resource(board);
+    Ok(true)
+}
+
+fn get_arrival_tile(world: &World, board: &Board, kind: InteractionKind) -> Option<Vector2i> {
+    // the closest free tile to the stair that the player came through
+    let query = world.query::<Interactive>().with::<Position>().build();
+    let stair = query.iter::<Interactive>().zip(query.iter::<Position>())
+        .find(|(i, _)| i.kind == kind)
+        .map(|(_, p)| p.0)?;
+    drop(query);
+
+    let mut visited = HashSet::from([stair]);
+    let mut queue = VecDeque::from([stair]);
+    while let Some(v) = queue.pop_front() {
+        for dir in ORTHO_DIRECTIONS {
+            let n = v + dir;
+            if !board.tiles.contains_key(&n) || !visited.insert(n) { continue }
+            let entities = get_entities_at_position(world, n);
+            if !entities.iter().any(|&e| world.get_component::<Obstacle>(e).is_some()
+                || world.get_component::<Interactive>(e).is_some()
+            ) {
+                return Some(n)
+            }
+            // search past the occupants, but not through the walls
+            if entities.iter().all(|&e| world.get_component::<Obstacle>(e).is_none()
+                || world.get_component::<Actor>(e).is_some()
+            ) {
+                queue.push_back(n);
+            }
+        }
+    }
+    None
+}
@@ -1,7 +1,59 @@
 pub const BOARD_SIZE: u32 = 8;
+// before the fallback layout is used
+pub const MAX_GENERATION_TRIES: u32 = 16;
+// bump on every change to the serialized components or resources
+pub const SAVE_VERSION: u32 = 1;
 pub const VIEW_RANGE: u32 = 5;
+// player's sight on dark floors, without a light source
+pub const DARK_VIEW_RANGE: u32 = 2;
+// max distance at which the lit tiles can be seen
+pub const LIT_VIEW_RANGE: u32 = 8;
+// dark floors, unless set in the level data
+pub const DARK_MIN_LEVEL: u32 = 5;
+pub const DARK_CHANCE: f64 = 0.3;
+// turns before an npc forgets the last seen player position
+pub const NPC_MEMORY: u32 = 4;
+// max distance from the post that a guard would chase the player
+pub const GUARD_RADIUS: i32 = 3;
+
+// noise volumes - max tile distance at which the sound is heard
+pub const STEP_NOISE: u32 = 2;
+pub const COMBAT_NOISE: u32 = 4;
 
 pub const LEVEL_COUNT: u32 = 20;
 
+// item spawn chances
+pub const CURSE_CHANCE: f64 = 0.1;
+pub const BLESS_CHANCE: f64 = 0.1;
+// rolled separately for the prefix and the suffix
+pub const AFFIX_CHANCE: f64 = 0.25;
+
+// chance of a floor holding a prefab room
+pub const PREFAB_CHANCE: f64 = 0.4;
+// special rooms, each rolled once per floor
+pub const TREASURE_ROOM_CHANCE: f64 = 0.3;
+pub const TREASURE_ROOM_MIN_LEVEL: u32 = 2;
+pub const DEN_CHANCE: f64 = 0.3;
+pub const DEN_MIN_LEVEL: u32 = 4;
+pub const DEN_MIN_AREA: u32 = 9;
+// share of the floor's npcs that spawn in the den
+pub const DEN_NPC_SHARE: f64 = 0.6;
+pub const SHRINE_ROOM_CHANCE: f64 = 0.5;
+
+// per turn, for each flammable neighbour
+pub const FIRE_SPREAD_CHANCE: f64 = 0.5;
+
+// traps per level, unless set in the level data
+pub const TRAP_DENSITY: f32 = 0.3;
+pub const MAX_TRAPS: usize = 4;
+// per turn, for each hidden trap next to the player
+pub const TRAP_REVEAL_CHANCE: f64 = 0.25;
+// tile distance searched by waiting
+pub const SEARCH_RANGE: i32 = 2;
+// per search, for each hidden trap in range
+pub const SEARCH_CHANCE: f64 = 0.5;
+
 pub const MAX_WEAPONS: usize = 4;
-pub const MAX_COLLECTABLES: usize = 4;
+// armor, shield and ring
+pub const EQUIPMENT_SLOTS: usize = 3;
+pub const MAX_COLLECTABLES: usize = 6;
@@ -0,0 +1,403 @@
+use rand::prelude::*;
+use rogalik::math::vectors::{Vector2i, ORTHO_DIRECTIONS};
+use std::collections::{HashSet, VecDeque};
+
+use hike_data::LayoutKind;
+
+use crate::board::{sort_tiles, tile_range};
+use crate::globals::BOARD_SIZE;
+use crate::utils::get_rng;
+
+// before falling back to the fixed layout
+const MAX_BSP_TRIES: u32 = 64;
+const CAVE_WALL_CHANCE: f64 = 0.4;
+const CAVE_STEPS: u32 = 2;
+const MIN_CAVE_FLOOR: usize = 36;
+// per each half of the board
+const MIN_CAVE_ROOM: usize = 8;
+// before falling back to the open layout
+const MAX_CAVE_TRIES: u32 = 16;
+
+pub trait LayoutGenerator {
+    fn generate(&self) -> Layout;
+    // whether pillars can be placed in the largest room
+    fn has_columns(&self) -> bool { false }
+}
+
+pub fn get_generator(kind: LayoutKind) -> Box<dyn LayoutGenerator> {
+    match kind {
+        LayoutKind::Arena => Box::new(ArenaGenerator),
+        LayoutKind::Bsp => Box::new(BspGenerator),
+        LayoutKind::Caves => Box::new(CaveGenerator),
+        LayoutKind::Maze => Box::new(MazeGenerator),
+        LayoutKind::Open => Box::new(OpenGenerator),
+        LayoutKind::Ring => Box::new(RingGenerator)
+    }
+}
+
+// the first room is the player's, the last one the largest
+pub struct Layout {
+    pub doors: HashSet<Vector2i>,
+    pub walls: HashSet<Vector2i>,
+    pub rooms: Vec<Room>
+}
+impl Layout {
+    pub fn get_exits(&self, room: &Room) -> HashSet<Vector2i> {
+        // tiles just outside of the room that can be walked through
+        let bounds = tile_range(
+            Vector2i::ZERO,
+            Vector2i::new(BOARD_SIZE as i32 - 1, BOARD_SIZE as i32 - 1)
+        );
+        let tiles = room.tiles();
+        tiles.iter()
+            .flat_map(|&v| ORTHO_DIRECTIONS.iter().map(move |&d| v + d))
+            .filter(|v| bounds.contains(v) && !tiles.contains(v) && !self.walls.contains(v))
+            .collect()
+    }
+}
+
+// purpose of a room, set during the generation
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum RoomKind {
+    // extra loot behind a locked door
+    Treasure,
+    // clustered npc spawns
+    Den,
+    // holds the floor's fixture
+    Shrine
+}
+
+pub struct Room {
+    pub a: Vector2i,
+    pub b: Vector2i,
+    pub doors: Vec<Vector2i>
+}
+impl Room {
+    pub fn tiles(&self) -> HashSet<Vector2i> {
+        tile_range(self.a, self.b)
+    }
+    pub fn area(&self) -> u32 {
+        let (w, h) = self.dim();
+        w * h
+    }
+    pub fn dim(&self) -> (u32, u32) {
+        // ((self.b.x - self.a.x).abs() as u32, (self.b.y - self.a.y).abs() as u32)
+        ((self.b.x - self.a.x) as u32 + 1, (self.b.y - self.a.y) as u32 + 1)
+    }
+}
+
+pub struct BspGenerator;
+impl LayoutGenerator for BspGenerator {
+    fn generate(&self) -> Layout {
+        get_bsp_layout()
+    }
+    fn has_columns(&self) -> bool { true }
+}
+
+fn get_bsp_layout() -> Layout {
+    'outer: for _ in 0..MAX_BSP_TRIES {
+        let base = Room {
+            a: Vector2i::ZERO,
+            b: Vector2i::new(BOARD_SIZE as i32 - 1, BOARD_SIZE as i32 - 1),
+            doors: Vec::new()
+        };
+        let mut wall_tiles = base.tiles();
+        let mut rooms = divide_room(base);
+        if rooms.len() < 3 { continue; }
+        let mut doors = HashSet::new();
+        for r in rooms.iter() {
+            doors.extend(&r.doors);
+            for v in r.tiles() {
+                wall_tiles.remove(&v);
+            }
+            for v in r.doors.iter() {
+                wall_tiles.remove(&v);
+            }
+        }
+
+        // extra door validation for safety
+        for door in doors.iter() {
+            let n = ORTHO_DIRECTIONS.iter()
+                .filter(|dir| wall_tiles.contains(&(*door + **dir)))
+                .count();
+            if n > 2 { continue 'outer }
+        }
+
+        rooms.sort_by(|a, b| a.area().cmp(&b.area()));
+
+        return Layout {
+            walls: wall_tiles,
+            doors,
+            rooms
+        };
+    }
+    get_fallback_layout()
+}
+
+pub fn get_fallback_layout() -> Layout {
+    // fixed three rooms, used when the random generation keeps failing
+    let max = BOARD_SIZE as i32 - 1;
+    let split = BOARD_SIZE as i32 / 2 - 1;
+    let door_a = Vector2i::new(split, split + 2);
+    let door_b = Vector2i::new(split + 2, split);
+    let walls = (0..=max).map(|y| Vector2i::new(split, y))
+        .chain((split + 1..=max).map(|x| Vector2i::new(x, split)))
+        .filter(|v| *v != door_a && *v != door_b)
+        .collect();
+    Layout {
+        walls,
+        doors: HashSet::from_iter([door_a, door_b]),
+        rooms: vec![
+            Room { a: Vector2i::new(split + 1, 0), b: Vector2i::new(max, split - 1), doors: vec![door_b] },
+            Room { a: Vector2i::new(split + 1, split + 1), b: Vector2i::new(max, max), doors: vec![door_a, door_b] },
+            Room { a: Vector2i::ZERO, b: Vector2i::new(split - 1, max), doors: vec![door_a] },
+        ]
+    }
+}
+
+pub struct ArenaGenerator;
+impl LayoutGenerator for ArenaGenerator {
+    fn generate(&self) -> Layout {
+        // a narrow antechamber and a single large hall
+        let mut rng = get_rng();
+        let max = BOARD_SIZE as i32 - 1;
+        let door = Vector2i::new(rng.gen_range(2..=max - 2), 2);
+        let walls = (0..=max).map(|x| Vector2i::new(x, 2))
+            .filter(|v| *v != door)
+            .collect();
+        Layout {
+            walls,
+            doors: HashSet::from_iter([door]),
+            rooms: vec![
+                Room { a: Vector2i::ZERO, b: Vector2i::new(max, 1), doors: vec![door] },
+                Room { a: Vector2i::new(0, 3), b: Vector2i::new(max, max), doors: vec![door] },
+            ]
+        }
+    }
+    fn has_columns(&self) -> bool { true }
+}
+
+pub struct OpenGenerator;
+impl LayoutGenerator for OpenGenerator {
+    fn generate(&self) -> Layout {
+        // a single hall, split only to keep the player's start apart
+        let max = BOARD_SIZE as i32 - 1;
+        Layout {
+            walls: HashSet::new(),
+            doors: HashSet::new(),
+            rooms: vec![
+                Room { a: Vector2i::ZERO, b: Vector2i::new(max, 1), doors: Vec::new() },
+                Room { a: Vector2i::new(0, 2), b: Vector2i::new(max, max), doors: Vec::new() },
+            ]
+        }
+    }
+    fn has_columns(&self) -> bool { true }
+}
+
+pub struct RingGenerator;
+impl LayoutGenerator for RingGenerator {
+    fn generate(&self) -> Layout {
+        // a corridor running around a walled central hall
+        let mut rng = get_rng();
+        let max = BOARD_SIZE as i32 - 1;
+        let mut walls = tile_range(Vector2i::new(1, 1), Vector2i::new(max - 1, max - 1));
+        walls.retain(|v| v.x == 1 || v.y == 1 || v.x == max - 1 || v.y == max - 1);
+
+        // at most one door per side
+        let door_count = rng.gen_range(2..=4);
+        let doors = [
+            Vector2i::new(rng.gen_range(3..=max - 3), 1),
+            Vector2i::new(rng.gen_range(3..=max - 3), max - 1),
+            Vector2i::new(1, rng.gen_range(3..=max - 3)),
+            Vector2i::new(max - 1, rng.gen_range(3..=max - 3)),
+        ].into_iter().choose_multiple(&mut rng, door_count);
+        for door in doors.iter() {
+            walls.remove(door);
+        }
+
+        let mut rooms = vec![
+            Room { a: Vector2i::ZERO, b: Vector2i::new(max, 0), doors: Vec::new() },
+            Room { a: Vector2i::new(0, max), b: Vector2i::new(max, max), doors: Vec::new() },
+            Room { a: Vector2i::new(0, 1), b: Vector2i::new(0, max - 1), doors: Vec::new() },
+            Room { a: Vector2i::new(max, 1), b: Vector2i::new(max, max - 1), doors: Vec::new() },
+        ];
+        rooms.shuffle(&mut rng);
+        rooms.push(
+            Room { a: Vector2i::new(2, 2), b: Vector2i::new(max - 2, max - 2), doors: doors.clone() }
+        );
+        Layout {
+            walls,
+            doors: HashSet::from_iter(doors),
+            rooms
+        }
+    }
+}
+
+pub struct MazeGenerator;
+impl LayoutGenerator for MazeGenerator {
+    fn generate(&self) -> Layout {
+        // recursive backtracker over the even tiles, the far edges are left open
+        let mut rng = get_rng();
+        let max = BOARD_SIZE as i32 - 1;
+        let mut walls = tile_range(Vector2i::ZERO, Vector2i::new(max - 1, max - 1));
+        let cells = walls.iter()
+            .filter(|v| v.x % 2 == 0 && v.y % 2 == 0)
+            .copied()
+            .collect::<HashSet<_>>();
+
+        let start = *sort_tiles(&cells).choose(&mut rng).unwrap();
+        let mut visited = HashSet::from_iter([start]);
+        let mut stack = vec![start];
+        walls.remove(&start);
+        while let Some(&current) = stack.last() {
+            let next = ORTHO_DIRECTIONS.iter()
+                .map(|&d| (d, Vector2i::new(current.x + 2 * d.x, current.y + 2 * d.y)))
+                .filter(|(_, v)| cells.contains(v) && !visited.contains(v))
+                .choose(&mut rng);
+            let Some((dir, next)) = next else {
+                stack.pop();
+                continue
+            };
+            walls.remove(&(current + dir));
+            walls.remove(&next);
+            visited.insert(next);
+            stack.push(next);
+        }
+
+        let mut rooms = get_quarters();
+        rooms.shuffle(&mut rng);
+        Layout {
+            walls,
+            doors: HashSet::new(),
+            rooms
+        }
+    }
+}
+
+pub struct CaveGenerator;
+impl LayoutGenerator for CaveGenerator {
+    fn generate(&self) -> Layout {
+        for _ in 0..MAX_CAVE_TRIES {
+            if let Some(layout) = get_cave_layout() { return layout }
+        }
+        OpenGenerator.generate()
+    }
+}
+
+fn get_cave_layout() -> Option<Layout> {
+    // cellular automata - the walls grow where they are already dense
+    let mut rng = get_rng();
+    let max = BOARD_SIZE as i32 - 1;
+    let area = tile_range(Vector2i::ZERO, Vector2i::new(max, max));
+    let mut walls = sort_tiles(&area).into_iter()
+        .filter(|_| rng.gen_bool(CAVE_WALL_CHANCE))
+        .collect::<HashSet<_>>();
+
+    for _ in 0..CAVE_STEPS {
+        walls = area.iter()
+            .filter(|v| {
+                let n = (-1..=1).flat_map(|x| (-1..=1).map(move |y| Vector2i::new(x, y)))
+                    .filter(|d| *d != Vector2i::ZERO && walls.contains(&(**v + *d)))
+                    .count();
+                n >= 5 || (n >= 4 && walls.contains(v))
+            })
+            .copied()
+            .collect();
+    }
+
+    // unreachable pockets are filled in
+    let open = area.difference(&walls).copied().collect::<HashSet<_>>();
+    let region = get_largest_region(&open);
+    if region.len() < MIN_CAVE_FLOOR { return None }
+
+    let half = BOARD_SIZE as i32 / 2;
+    let mut rooms = vec![
+        Room { a: Vector2i::ZERO, b: Vector2i::new(half - 1, max), doors: Vec::new() },
+        Room { a: Vector2i::new(half, 0), b: Vector2i::new(max, max), doors: Vec::new() },
+    ];
+    if rooms.iter().any(|r| r.tiles().intersection(&region).count() < MIN_CAVE_ROOM) {
+        return None
+    }
+    rooms.shuffle(&mut rng);
+
+    Some(Layout {
+        walls: area.difference(&region).copied().collect(),
+        doors: HashSet::new(),
+        rooms
+    })
+}
+
+fn get_largest_region(tiles: &HashSet<Vector2i>) -> HashSet<Vector2i> {
+    // flood fill over the orthogonal neighbours
+    // started in the sorted order, so that the ties keep the same region
+    let mut left = tiles.clone();
+    let mut largest = HashSet::new();
+    for start in sort_tiles(tiles) {
+        if !left.contains(&start) { continue }
+        let mut region = HashSet::from_iter([start]);
+        let mut queue = VecDeque::from([start]);
+        while let Some(v) = queue.pop_front() {
+            for dir in ORTHO_DIRECTIONS.iter() {
+                let n = v + *dir;
+                if left.contains(&n) && region.insert(n) { queue.push_back(n) }
+            }
+        }
+        left.retain(|v| !region.contains(v));
+        if region.len() > largest.len() { largest = region }
+    }
+    largest
+}
+
+fn get_quarters() -> Vec<Room> {
+    let half = BOARD_SIZE as i32 / 2;
+    let max = BOARD_SIZE as i32 - 1;
+    [(0, 0), (half, 0), (0, half), (half, half)].iter()
+        .map(|&(x, y)| Room {
+            a: Vector2i::new(x, y),
+            b: Vector2i::new((x + half - 1).min(max), (y + half - 1).min(max)),
+            doors: Vec::new()
+        })
+        .collect()
+}
+
+fn divide_room(r: Room) -> Vec<Room> {
+    let dx = r.b.x - r.a.x;
+    let dy = r.b.y - r.a.y;
+    if  dx < 4 && dy < 4 { return vec![r] }
+    let vertical = dx < dy;
+    let mut rng = get_rng();
+
+    let split_val = if vertical { rng.gen_range(r.a.y + 2..r.b.y -1 ) }
+        else { rng.gen_range(r.a.x + 2..r.b.x -1 ) };
+
+    // check existing door collision
+    if vertical && r.doors.iter().any(|&v| v.x == split_val) { return vec![r] }
+        else {
+            if r.doors.iter().any(|&v| v.y == split_val) { return vec![r] }
+        }
+
+    let corner_a = if vertical { Vector2i::new(r.b.x, split_val - 1) } else { Vector2i::new(split_val - 1, r.b.y) };
+    let corner_b = if vertical { Vector2i::new(r.a.x, split_val + 1) } else { Vector2i::new(split_val + 1, r.a.y) };
+
+    let mut doors = r.doors.clone();
+    let door = get_bsp_door(vertical, split_val, r.a, r.b);
+    
+    // consider extra door for large rooms
+    if dx.max(dy) > 5 && rng.gen_bool(0.75) {
+        let extra_door = get_bsp_door(vertical, split_val, r.a, r.b);
+        if extra_door.manhattan(door) > 1 { doors.push(extra_door) };
+    }
+    doors.push(door);
+
+    let room_a = Room { a: r.a, b: corner_a, doors: doors.clone() };
+    let room_b = Room { a: corner_b, b: r.b, doors };
+    let mut res = divide_room(room_a);
+    res.extend(divide_room(room_b));
+    res
+}
+
+fn get_bsp_door(vertical: bool, split_val: i32, a: Vector2i, b: Vector2i) -> Vector2i {
+    let mut rng = get_rng();
+    if vertical { Vector2i::new(rng.gen_range(a.x..=b.x), split_val) }
+        else { Vector2i::new(split_val, rng.gen_range(a.y..=b.y))}
+}
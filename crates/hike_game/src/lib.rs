@@ -15,7 +15,9 @@ pub mod actions;
 mod board;
 pub mod components;
 mod events;
+mod floors;
 pub mod globals;
+mod layouts;
 mod player;
 pub mod structs;
 mod systems;
@@ -24,19 +26,39 @@ mod utils;
 pub use player::{set_player_action, set_player_action_from_dir, get_player_position, get_player_entity};
 pub use board::Board;
 pub use events::GameEvent;
-pub use utils::get_entities_at_position;
+pub use floors::{Floor, Floors, StoredEntity, restore_floor, store_floor};
+pub use utils::{get_entities_at_position, get_stack_size, get_stack_target, get_rng, seed_rng, validate_affixes};
 
 pub fn init(world: &mut World, events: &mut EventBus<GameEvent>, data: hike_data::GameData) {
     world.insert_resource(GameStats::new());
     world.insert_resource(data);
-    systems::board_start(world, events);
+    world.insert_resource(Floors::default());
+    systems::board_start(world, events, 1, None);
 }
 
-pub fn restore(world: &mut World, data: hike_data::GameData, saved_state: Vec<u8>) {
-    world.deserialize(&saved_state).unwrap();
+pub fn register_serialized(world: &mut World) {
+    world.register_serializable_resource::<Board>("Board");
+    world.register_serializable_resource::<Floors>("Floors");
+    world.register_serializable_resource::<GameStats>("GameStats");
+
+    components::register_serializable_components(world);
+}
+
+pub fn save(world: &World) -> Result<Vec<u8>, ()> {
+    // prefixed with the version, so that outdated saves can be discarded
+    let mut data = globals::SAVE_VERSION.to_le_bytes().to_vec();
+    data.extend(world.serialize().map_err(|_| ())?);
+    Ok(data)
+}
+
+pub fn restore(world: &mut World, data: hike_data::GameData, saved_state: Vec<u8>) -> Result<(), ()> {
+    let version = saved_state.get(..4).ok_or(())?;
+    if version != globals::SAVE_VERSION.to_le_bytes() { return Err(()) }
+    world.deserialize(&saved_state[4..]).map_err(|_| ())?;
     world.insert_resource(data);
     world.insert_resource(actions::PendingActions(VecDeque::new()));
     world.insert_resource(actions::ActorQueue(VecDeque::new()));
+    Ok(())
 }
 
 #[derive(Serialize, Deserialize)]
@@ -55,9 +77,10 @@ impl GameStats {
 }
 
 pub fn game_update(world: &mut World, events: &mut EventBus<GameEvent>) -> Result<(), ()> {
-    if world.get_resource::<Board>().ok_or(())?.is_exit() {
+    let exit = world.get_resource::<Board>().ok_or(())?.exit.clone();
+    if let Some((level, branch)) = exit {
         systems::board_end(world);
-        systems::board_start(world, events);
+        systems::board_start(world, events, level, branch);
         return Ok(());
     }
     systems::turn_step(world, events);
@@ -1,13 +1,15 @@
+use rand::prelude::*;
 use rogalik::{
     math::vectors::Vector2i,
     storage::{Entity, World}
 };
 
-use crate::actions::{Action, ActorQueue, get_action_at_dir};
+use crate::actions::{Action, ActorQueue, PendingActions, RevealTrap, get_action_at_dir};
 use crate::board::Board;
-use crate::components::{Position, Player};
+use crate::components::{Hidden, Position, Player};
+use crate::globals::TRAP_REVEAL_CHANCE;
 
-use crate::utils::spawn_with_position;
+use crate::utils::{get_rng, spawn_with_position};
 
 pub fn spawn_player(world: &mut World) {
     let position = if let Some(board) = world.get_resource::<Board>() {
@@ -35,6 +37,12 @@ pub fn get_player_entity(world: &World) -> Option<Entity> {
         .build().single_entity()
 }
 
+pub fn get_worn_items(world: &World, entity: Entity) -> Vec<Entity> {
+    // only the player can wear equipment
+    let Some(player) = world.get_component::<Player>(entity) else { return Vec::new() };
+    player.equipment.iter().flatten().copied().collect()
+}
+
 pub fn set_player_action_from_dir(
     world: &mut World,
     dir: Vector2i
@@ -70,6 +78,20 @@ pub fn turn_end(world: &mut World) {
     //     world.get_component_mut::<Player>(item.entity)
     //         .unwrap().active_ability = 0;
     // }
+    reveal_adjacent_traps(world);
+}
+
+fn reveal_adjacent_traps(world: &mut World) {
+    let Some(position) = get_player_position(world) else { return };
+    let mut rng = get_rng();
+    let query = world.query::<Hidden>().with::<Position>().build();
+    let actions = query.iter::<Position>().zip(query.entities())
+        .filter(|(p, _)| p.0.manhattan(position) == 1 && rng.gen_bool(TRAP_REVEAL_CHANCE))
+        .map(|(_, &entity)| Box::new(RevealTrap { entity }) as Box<dyn Action>)
+        .collect::<Vec<_>>();
+
+    let Some(mut pending) = world.get_resource_mut::<PendingActions>() else { return };
+    pending.0.extend(actions);
 }
 
 
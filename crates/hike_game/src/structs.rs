@@ -6,8 +6,9 @@ use serde::{Serialize, Deserialize, Deserializer};
 use serde::de::Visitor;
 
 use crate::actions::{
-    Action, Heal, PickGold, GiveImmunity, GiveRegeneration,
-    HitAction, StunAction, PoisonAction, ApplyPoison, HealPoison, Teleport, WinAction
+    Action, Heal, PickGold, PickKey, GiveImmunity, GiveRegeneration,
+    HitAction, StunAction, PoisonAction, ApplyPoison, HealPoison, Teleport, WinAction,
+    GiveStealth
 };
 use crate::utils::deserialize_random_u32;
 
@@ -20,7 +21,39 @@ pub enum Attitude {
     Panic
 }
 
-#[derive(Clone, Copy, Deserialize, Serialize)]
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq)]
+pub enum Behaviour {
+    #[default]
+    Idle,
+    Sleeping,
+    // wanders between the board rooms
+    Patrol,
+    // does not leave the vicinity of it's home tile
+    Guard
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq)]
+pub enum EquipmentSlot {
+    Armor,
+    Shield,
+    Ring
+}
+
+#[derive(Clone, Deserialize, Serialize)]
+pub struct Offer {
+    pub name: String,
+    pub price: u32
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct Phase {
+    // health level at which the phase ends
+    pub health: u32,
+    // entity that takes over in the next phase
+    pub next: String
+}
+
+#[derive(Clone, Copy, Deserialize, Serialize, PartialEq)]
 pub enum AttackKind {
     Hit,
     Poison,
@@ -34,17 +67,24 @@ pub struct Attack {
     pub value: u32
 }
 
-#[derive(Deserialize, Serialize)]
+#[derive(Clone, Copy, Deserialize, Serialize)]
 pub enum EffectKind {
     Gold,
     Heal,
     HealPoison,
     Immunity,
+    Key,
     Poison,
     Regenerate,
+    Stealth,
     Teleport,
     Win
 }
+impl EffectKind {
+    pub fn is_harmful(&self) -> bool {
+        matches!(self, EffectKind::Poison)
+    }
+}
 
 #[derive(Deserialize, Serialize)]
 pub struct Effect {
@@ -52,6 +92,48 @@ pub struct Effect {
     #[serde(deserialize_with="deserialize_random_u32")]
     pub value: u32
 }
+impl Effect {
+    pub fn with_blessing(&self, blessing: Option<BlessingKind>) -> Effect {
+        // blessings work in the drinker's favour, so the harmful effects are scaled the other way
+        let blessing = match blessing {
+            Some(BlessingKind::Blessed) if self.kind.is_harmful() => Some(BlessingKind::Cursed),
+            Some(BlessingKind::Cursed) if self.kind.is_harmful() => Some(BlessingKind::Blessed),
+            a => a
+        };
+        let value = match blessing {
+            Some(BlessingKind::Blessed) => self.value + self.value.div_ceil(2),
+            Some(BlessingKind::Cursed) => self.value / 2,
+            None => self.value
+        };
+        Effect { kind: self.kind, value }
+    }
+}
+
+#[derive(Clone, Deserialize, Serialize)]
+pub enum TrapEffect {
+    // attacks the trap's tile
+    Darts(Attack),
+    // noise volume
+    Alarm(u32),
+    // creature spawned next to the trap
+    Summon(String),
+    // hazard spawned on the trap's tile
+    Hazard(String)
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq, Hash)]
+pub enum HazardKind {
+    // spreads over flammable tiles
+    Fire,
+    // spreads freely, thinning out
+    Gas
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq)]
+pub enum BlessingKind {
+    Blessed,
+    Cursed
+}
 
 #[derive(Serialize)]
 pub struct ValueMax {
@@ -117,8 +199,9 @@ impl<'de> Visitor<'de> for ValueMaxVisitor {
         let mut current = None;
         let mut max = None;
 
-        while let Some(k) = map.next_key()? {
-            match k {
+        // owned keys, as the values read back from the saves do not borrow
+        while let Some(k) = map.next_key::<String>()? {
+            match k.as_str() {
                 "current" | "Current" | "CURRENT" => current = Some(map.next_value()?),
                 "max" | "Max" | "MAX" => max = Some(map.next_value()?),
                 _ => ()
@@ -134,15 +217,35 @@ impl<'de> Visitor<'de> for ValueMaxVisitor {
 #[derive(Deserialize, Serialize, PartialEq)]
 pub enum InteractionKind {
     Ascend,
+    // ascends into the side branch
+    Branch(String),
+    Descend,
+    Gamble(#[serde(deserialize_with="deserialize_random_u32")] u32),
+    Identify,
+    // drops the Loot
+    Open,
     Repair(#[serde(deserialize_with="deserialize_random_u32")] u32),
+    // max health traded for the Loot
+    Sacrifice(#[serde(deserialize_with="deserialize_random_u32")] u32),
     UpgradeHealth(#[serde(deserialize_with="deserialize_random_u32")] u32),
+    UpgradeOffensive(#[serde(deserialize_with="deserialize_random_u32")] u32),
+    // as Open, but consumes a key
+    Unlock
 }
 impl InteractionKind {
     pub fn to_str(&self) -> String {
         match self {
             InteractionKind::Ascend => "Ascend".to_string(),
+            InteractionKind::Branch(b) => format!("Enter {}", b.replace("_", " ")),
+            InteractionKind::Descend => "Descend".to_string(),
+            InteractionKind::Gamble(_) => "Gamble".to_string(),
+            InteractionKind::Identify => "Identify".to_string(),
+            InteractionKind::Open => "Open".to_string(),
             InteractionKind::Repair(v) => format!("Repair({})", v),
+            InteractionKind::Sacrifice(v) => format!("Decr. HP({})", v),
             InteractionKind::UpgradeHealth(v) => format!("Incr. HP({})", v),
+            InteractionKind::UpgradeOffensive(v) => format!("Enchant({})", v),
+            InteractionKind::Unlock => "Unlock Key(1)".to_string(),
         }
     }
 }
@@ -164,12 +267,18 @@ pub fn get_effect_action(
         EffectKind::Immunity => Box::new(
             GiveImmunity { entity, value: effect.value }
         ),
+        EffectKind::Key => Box::new(
+            PickKey { value: effect.value }
+        ),
         EffectKind::Poison => Box::new(
             ApplyPoison { entity, value: effect.value }
         ),
         EffectKind::Regenerate => Box::new(
             GiveRegeneration { entity, value: effect.value }
         ),
+        EffectKind::Stealth => Box::new(
+            GiveStealth { entity, value: effect.value }
+        ),
         EffectKind::Teleport => Box::new(
             Teleport { entity }
         ),
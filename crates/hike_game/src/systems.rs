@@ -1,33 +1,52 @@
 use rogalik::{
     events::EventBus,
-    math::vectors::Vector2i,
+    math::vectors::{Vector2i, ORTHO_DIRECTIONS},
     storage::{Entity, World}
 };
-use std::collections::{HashMap, VecDeque};
+use rand::prelude::*;
+use std::collections::{HashMap, HashSet, VecDeque};
 
 use crate::GameStats;
 use crate::actions::{
-    Action, ActorQueue, AttackAction, Damage, DropLoot, Heal, PendingActions, UseInstant, get_npc_action,
+    Action, ActorQueue, AttackAction, Damage, DropLoot, Heal, MakeNoise, NextPhase, PendingActions,
+    UseInstant, get_npc_action,
 };
-use crate::board::{Board, update_visibility};
+use crate::board::{Board, replace_tile, tile_range, update_visibility};
 use crate::components::{
-    Actor, Durability, Fixture, Immune, Instant, Stunned, Health, Offensive, Projectile, Regeneration,
-    Player, Position, Poisoned, Transition, Name, Ranged, Summoner
+    Actor, Boss, Durability, Fixture, Flammable, Hazard, Immaterial, Immune, Instant, Stunned, Health,
+    Mending, Obstacle, Offensive, Projectile, Regeneration, Player, Position, Poisoned, Transition, Name,
+    Noise, Ranged, Slowed, Stealthy, Summoner, Terrain
 };
 use crate::events::GameEvent;
+use crate::floors::{restore_floor, store_floor};
+use crate::globals::{FIRE_SPREAD_CHANCE, GUARD_RADIUS, LEVEL_COUNT, NPC_MEMORY};
 use crate::player;
-use crate::structs::{get_attack_action, Attitude};
-use crate::utils::{get_entities_at_position, spawn_with_position};
+use crate::structs::{get_attack_action, Attitude, Behaviour, HazardKind};
+use crate::utils::{field_of_view, get_entities_at_position, get_rng, get_terrain, spawn_with_position};
+
+pub fn board_start(
+    world: &mut World,
+    events: &mut EventBus<GameEvent>,
+    level: u32,
+    branch: Option<String>
+) {
+    let previous = world.get_resource::<Board>().map(|b| (b.level, b.branch.clone()));
 
-pub fn board_start(world: &mut World, events: &mut EventBus<GameEvent>) {
     // replace board resource
-    let level = match world.get_resource::<Board>() {
-        Some(b) => b.level,
-        _=> 0
-    };
-    let mut board = Board::new(level + 1);
-    board.generate(world);
-    world.insert_resource(board);
+    // a floor that could not be restored is generated anew
+    if restore_floor(world, level, &branch) != Ok(true) {
+        let mut board = Board::new(level, branch.clone());
+        // items spawned during the generation read the level from the resource
+        world.insert_resource(Board::new(level, branch));
+        board.generate(world);
+        world.insert_resource(board);
+    }
+    // remember the way back
+    if let Some((previous_level, previous_branch)) = previous {
+        if let Some(mut board) = world.get_resource_mut::<Board>() {
+            if previous_level < board.level { board.below = previous_branch }
+        }
+    }
 
     // reset queues
     let queue = ActorQueue(VecDeque::new());
@@ -43,6 +62,8 @@ pub fn board_start(world: &mut World, events: &mut EventBus<GameEvent>) {
 pub fn board_end(world: &mut World) {
     // unpin player
     player::unpin_player(world);
+    // keep the floor for backtracking
+    store_floor(world);
     // despawn board objects
     let to_remove = world.query::<Position>().build().entities()
         .copied().collect::<Vec<_>>();
@@ -55,6 +76,7 @@ pub fn turn_step(world: &mut World, events: &mut EventBus<GameEvent>) {
     hit_projectiles(world, events);
     update_visibility(world);
     kill_units(world, events);
+    process_boss_phases(world, events);
     destroy_items(world);
     handle_instant(world);
     if process_pending_action(world, events) {
@@ -80,6 +102,7 @@ fn get_current_actor(world: &mut World) -> Option<Entity> {
 fn process_actor(entity: Entity, world: &mut World, events: &mut EventBus<GameEvent>) -> bool {
     // returns true if the actor is done
     if process_stunned(world, entity) { return true };
+    if process_slowed(world, entity) { return true };
     let Some(selected) = get_new_action(entity, world) else { return false };
     execute_action(selected, world, events).is_ok()
 }
@@ -112,6 +135,9 @@ fn execute_action(
         events.publish(action.event());
         return Ok(())
     }
+    if let Some(event) = action.failure_event(world) {
+        events.publish(event);
+    }
     Err(())
 }
 
@@ -184,6 +210,11 @@ fn destroy_items(
                     player.weapons[idx] = None;
                 }
             }
+            for idx in 0..player.equipment.len() {
+                if player.equipment[idx] == Some(entity) {
+                    player.equipment[idx] = None;
+                }
+            }
             player.collectables.retain(|&e| e != entity);
         }
     }
@@ -215,10 +246,50 @@ fn kill_units(world: &mut World, events: &mut EventBus<GameEvent>) {
             world,
             events
         );
+        let noise = match (world.get_component::<Noise>(entity), world.get_component::<Position>(entity)) {
+            (Some(noise), Some(position)) => Some(MakeNoise { source: position.0, volume: noise.0 }),
+            _ => None
+        };
+        if let Some(noise) = noise {
+            let _ = execute_action(Box::new(noise), world, events);
+        }
+        spawn_boss_exit(world, entity);
         world.despawn_entity(entity);
     }
 }
 
+fn spawn_boss_exit(world: &mut World, entity: Entity) {
+    // defeated bosses reveal the way up
+    if world.get_component::<Boss>(entity).is_none() { return }
+    let Some(level) = world.get_resource::<Board>().map(|b| b.level) else { return };
+    if level >= LEVEL_COUNT { return }
+    let Some(position) = world.get_component::<Position>(entity).map(|p| p.0) else { return };
+    spawn_with_position(world, "Stair", position);
+}
+
+fn process_boss_phases(world: &mut World, events: &mut EventBus<GameEvent>) {
+    let query = world.query::<Boss>().with::<Health>().with::<Position>().build();
+    let to_advance = query.iter::<Boss>()
+        .zip(query.iter::<Health>())
+        .zip(query.iter::<Position>())
+        .zip(query.entities())
+        .filter(|(((boss, health), _), _)| match &boss.phase {
+            Some(phase) => health.0.current <= phase.health,
+            None => false
+        })
+        .map(|((_, position), &entity)| (entity, position.0))
+        .collect::<Vec<_>>();
+    drop(query);
+
+    for (entity, position) in to_advance {
+        let _ = execute_action(
+            Box::new(NextPhase { entity, position }),
+            world,
+            events
+        );
+    }
+}
+
 fn collect_actor_queue(world: &mut World) {
     let Some(mut queue) = world.get_resource_mut::<ActorQueue>() else { return };
     let mut actors = world.query::<Actor>().build().entities().copied().collect::<Vec<_>>();
@@ -240,6 +311,13 @@ fn process_stunned(world: &mut World, entity: Entity) -> bool {
     true
 }
 
+fn process_slowed(world: &mut World, entity: Entity) -> bool {
+    // returns true if the actor skips the current turn
+    if world.get_component::<Slowed>(entity).is_none() { return false }
+    world.remove_component::<Slowed>(entity);
+    true
+}
+
 fn process_poisoned(world: &mut World) {
     let mut to_remove = Vec::new();
     let Some(mut pending) = world.get_resource_mut::<PendingActions>() else { return };
@@ -271,6 +349,20 @@ fn process_immune(world: &mut World) {
     }
 }
 
+fn process_stealthy(world: &mut World) {
+    let mut to_remove = Vec::new();
+    let query = world.query::<Stealthy>().build();
+    for (mut stealthy, &entity) in query.iter_mut::<Stealthy>().zip(query.entities()) {
+        stealthy.0 = stealthy.0.saturating_sub(1);
+        if stealthy.0 == 0 {
+            to_remove.push(entity);
+        }
+    }
+    for entity in to_remove {
+        world.remove_component::<Stealthy>(entity);
+    }
+}
+
 fn process_regeneration(world: &mut World) {
     let mut to_remove = Vec::new();
     let query = world.query::<Regeneration>().build();
@@ -288,6 +380,19 @@ fn process_regeneration(world: &mut World) {
     }
 }
 
+fn process_mending(world: &mut World) {
+    let Some(player_entity) = player::get_player_entity(world) else { return };
+    let worn = player::get_worn_items(world, player_entity);
+    let Some(mut pending) = world.get_resource_mut::<PendingActions>() else { return };
+    for entity in worn {
+        let Some(mut mending) = world.get_component_mut::<Mending>(entity) else { continue };
+        mending.cooldown.current = mending.cooldown.current.saturating_sub(1);
+        if mending.cooldown.current > 0 { continue }
+        mending.cooldown.current = mending.cooldown.max;
+        pending.0.push_back(Box::new(Heal { entity: player_entity, value: 1 }));
+    }
+}
+
 fn process_offensive_fixtures(world: &mut World) {
     let query = world.query::<Fixture>()
         .with::<Offensive>()
@@ -302,6 +407,120 @@ fn process_offensive_fixtures(world: &mut World) {
     }
 }
 
+fn process_hazards(world: &mut World) {
+    let mut rng = get_rng();
+    let query = world.query::<Hazard>().with::<Position>().with::<Name>().build();
+    let hazards = query.iter::<Hazard>()
+        .zip(query.iter::<Position>())
+        .zip(query.iter::<Name>())
+        .zip(query.entities())
+        .map(|(((h, p), n), &e)| (e, h.kind, h.strength, p.0, n.0.clone()))
+        .collect::<Vec<_>>();
+    drop(query);
+
+    let mut covered = hazards.iter().map(|h| (h.1, h.3)).collect::<HashSet<_>>();
+    let mut to_spawn = Vec::new();
+    let mut to_remove = Vec::new();
+    let mut burnt = Vec::new();
+
+    for (entity, kind, strength, v, name) in hazards {
+        if kind == HazardKind::Fire && get_terrain(world, v).map_or(false, |t| t.extinguishing) {
+            to_remove.push(entity);
+            continue
+        }
+        for dir in ORTHO_DIRECTIONS {
+            let n = v + dir;
+            if covered.contains(&(kind, n)) { continue }
+            let next_strength = match kind {
+                HazardKind::Fire if is_flammable(world, n) && rng.gen_bool(FIRE_SPREAD_CHANCE) => None,
+                // the gas thins out as it spreads
+                HazardKind::Gas if strength > 1 && !is_gas_blocked(world, n) => Some(strength - 1),
+                _ => continue
+            };
+            covered.insert((kind, n));
+            to_spawn.push((name.clone(), n, next_strength));
+        }
+        if strength <= 1 {
+            to_remove.push(entity);
+            if kind == HazardKind::Fire { burnt.push(v) }
+        } else if let Some(mut hazard) = world.get_component_mut::<Hazard>(entity) {
+            hazard.strength -= 1;
+        }
+    }
+
+    // igniting terrain (eg. lava) sets its surroundings on fire
+    if let Some(board) = world.get_resource::<Board>() {
+        for (&v, &tile) in board.tiles.iter() {
+            if !world.get_component::<Terrain>(tile).map_or(false, |t| t.igniting) { continue }
+            for dir in ORTHO_DIRECTIONS {
+                let n = v + dir;
+                if covered.contains(&(HazardKind::Fire, n)) || !is_flammable(world, n) { continue }
+                if !rng.gen_bool(FIRE_SPREAD_CHANCE) { continue }
+                covered.insert((HazardKind::Fire, n));
+                to_spawn.push(("Fire".to_string(), n, None));
+            }
+        }
+    }
+
+    for entity in to_remove {
+        world.despawn_entity(entity);
+    }
+    for v in burnt {
+        burn_tile(world, v);
+    }
+    for (name, v, strength) in to_spawn {
+        let Some(entity) = spawn_with_position(world, &name, v) else { continue };
+        let Some(strength) = strength else { continue };
+        if let Some(mut hazard) = world.get_component_mut::<Hazard>(entity) {
+            hazard.strength = strength;
+        }
+    }
+}
+
+fn is_flammable(world: &World, v: Vector2i) -> bool {
+    let terrain = get_terrain(world, v).unwrap_or_default();
+    if terrain.extinguishing { return false }
+    terrain.burnt.is_some() || get_entities_at_position(world, v).iter()
+        .any(|&e| world.get_component::<Flammable>(e).is_some())
+}
+
+fn is_gas_blocked(world: &World, v: Vector2i) -> bool {
+    let Some(board) = world.get_resource::<Board>() else { return true };
+    if !board.tiles.contains_key(&v) { return true }
+    get_entities_at_position(world, v).iter()
+        .any(|&e| world.get_component::<Obstacle>(e).is_some() && world.get_component::<Fixture>(e).is_some())
+}
+
+fn burn_tile(world: &mut World, v: Vector2i) {
+    let burnt = get_entities_at_position(world, v).into_iter()
+        .filter(|&e| world.get_component::<Flammable>(e).is_some())
+        .collect::<Vec<_>>();
+    for entity in burnt {
+        // burnt containers still spill their contents
+        let _ = DropLoot { entity }.execute(world);
+        world.despawn_entity(entity);
+    }
+    if let Some(next) = get_terrain(world, v).and_then(|t| t.burnt) {
+        replace_tile(world, v, &next);
+    }
+}
+
+fn process_terrain(world: &mut World) {
+    // harmful terrain damages everything standing on it
+    let query = world.query::<Health>().with::<Position>().build();
+    let actions = query.iter::<Position>().zip(query.entities())
+        .filter(|(_, &e)| world.get_component::<Immaterial>(e).is_none())
+        .filter_map(|(p, &entity)| {
+            let value = get_terrain(world, p.0)?.damage;
+            if value == 0 { return None }
+            Some(Box::new(Damage { entity, value }) as Box<dyn Action>)
+        })
+        .collect::<Vec<_>>();
+
+    let Some(mut pending) = world.get_resource_mut::<PendingActions>() else { return };
+    pending.0.extend(actions);
+}
+
 fn process_transition(world: &mut World) {
     let query = world.query::<Transition>().with::<Position>().build();
     let mut to_despawn = Vec::new();
@@ -335,14 +554,43 @@ fn update_npc_target(world: &mut World, entity: Entity) {
     if Some(position.0) == actor.target {
         actor.target = None
     };
-    let Some(player_v) = player::get_player_position(world) else { return };
-    if crate::utils::visibility(world, position.0, player_v) {
-        actor.target = Some(player_v);
-        match actor.attitude {
-            Attitude::Aware => actor.attitude = Attitude::Hostile,
-            Attitude::Neutral => actor.attitude = Attitude::Aware,
+    let player_position = player::get_player_position(world);
+
+    if actor.behaviour == Behaviour::Sleeping {
+        // sleepers can only be woken up by a close contact
+        match player_position {
+            Some(player_v) if player_v.manhattan(position.0) <= 1 => {
+                actor.behaviour = Behaviour::Idle;
+                actor.attitude = Attitude::Aware;
+                actor.target = Some(player_v);
+                actor.memory = NPC_MEMORY;
+            },
             _ => ()
         }
+        return
+    }
+    if actor.behaviour == Behaviour::Guard && actor.home.is_none() {
+        actor.home = Some(position.0);
+    }
+
+    let fov = field_of_view(world, position.0, actor.view_range);
+    match player_position {
+        Some(player_v) if fov.contains(&player_v) && is_guarded(&actor, player_v) => {
+            actor.target = Some(player_v);
+            actor.memory = NPC_MEMORY;
+            match actor.attitude {
+                // guards do not give warnings
+                Attitude::Neutral | Attitude::Aware if actor.behaviour == Behaviour::Guard
+                    => actor.attitude = Attitude::Hostile,
+                Attitude::Aware => actor.attitude = Attitude::Hostile,
+                Attitude::Neutral => actor.attitude = Attitude::Aware,
+                _ => ()
+            }
+        },
+        _ => decay_npc_memory(&mut actor)
+    }
+    if actor.target.is_none() {
+        actor.target = get_idle_target(world, &actor, position.0);
     }
     if let Some(health) = world.get_component::<Health>(entity) {
         if health.0.current == 1 && health.0.max > 7 {
@@ -351,15 +599,71 @@ fn update_npc_target(world: &mut World, entity: Entity) {
     }
 }
 
+fn is_guarded(actor: &Actor, v: Vector2i) -> bool {
+    // whether the tile is within the actor's area of interest
+    if actor.behaviour != Behaviour::Guard { return true }
+    let Some(home) = actor.home else { return true };
+    home.manhattan(v) <= GUARD_RADIUS
+}
+
+fn get_idle_target(world: &World, actor: &Actor, position: Vector2i) -> Option<Vector2i> {
+    match actor.behaviour {
+        Behaviour::Guard => {
+            let home = actor.home?;
+            if home.manhattan(position) > 1 { Some(home) } else { None }
+        },
+        Behaviour::Patrol => {
+            let board = world.get_resource::<Board>()?;
+            let mut rng = get_rng();
+            let room = board.rooms.choose(&mut rng)?;
+            let blockers = world.query::<Obstacle>().with::<Position>().build().iter::<Position>()
+                .map(|p| p.0)
+                .collect::<HashSet<_>>();
+            tile_range(room.0, room.1).into_iter()
+                .filter(|v| !blockers.contains(v))
+                .collect::<Vec<_>>()
+                .choose(&mut rng)
+                .copied()
+        },
+        _ => None
+    }
+}
+
+fn decay_npc_memory(actor: &mut Actor) {
+    // called when the player is out of npc's sight
+    if actor.memory > 0 {
+        actor.memory -= 1;
+        if actor.memory > 0 { return }
+    }
+    // the player is lost - calm down step by step
+    match actor.attitude {
+        Attitude::Hostile => {
+            actor.attitude = Attitude::Aware;
+            actor.memory = NPC_MEMORY;
+            actor.target = None;
+        },
+        Attitude::Aware => {
+            actor.attitude = Attitude::Neutral;
+            actor.target = None;
+        },
+        // idle targets, like the patrol points, are kept
+        _ => ()
+    }
+}
+
 fn turn_end(world: &mut World, events: &mut EventBus<GameEvent>) {
     collect_actor_queue(world);
     player::turn_end(world);
     process_regeneration(world);
+    process_mending(world);
     process_poisoned(world);
     process_immune(world);
+    process_stealthy(world);
     process_transition(world);
+    process_hazards(world);
     process_summoner_cooldown(world);
     process_offensive_fixtures(world);
+    process_terrain(world);
     events.publish(GameEvent::TurnEnd);
 }
 
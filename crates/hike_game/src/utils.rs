@@ -1,17 +1,53 @@
 use rand::prelude::*;
 use rogalik::{
-    math::vectors::{Vector2i, get_line},
+    math::vectors::{Vector2i, ORTHO_DIRECTIONS, visible_tiles},
     storage::{Entity, World}
 };
 use serde::{Deserialize, Deserializer, Serializer};
 use serde::de::Visitor;
+use std::{cell::RefCell, collections::HashSet, rc::Rc};
 
-use hike_data::GameData;
+use hike_data::{AffixData, AffixKind, GameData};
 
-use crate::components::{Actor, Name, Player, Position, ViewBlocker, insert_data_components};
-use crate::globals::VIEW_RANGE;
-use crate::structs::Attitude;
+use crate::board::Board;
+use crate::components::{
+    Actor, Affixes, Armor, Blessing, Collectable, Durability, Effects, Equipment, Fixture, Loot, Name,
+    Obstacle, Offensive, Player, Position, Resistance, Stack, Terrain, ViewBlocker, Weapon,
+    insert_data_components
+};
+use crate::globals::{AFFIX_CHANCE, BLESS_CHANCE, CURSE_CHANCE};
+use crate::player::{get_player_entity, get_worn_items};
+use crate::structs::{Attack, AttackKind, Attitude, BlessingKind};
+
+thread_local! {
+    static RNG: GameRng = GameRng(Rc::new(RefCell::new(StdRng::from_entropy())));
+}
+
+// shared game randomness, can be seeded to replay a run
+#[derive(Clone)]
+pub struct GameRng(Rc<RefCell<StdRng>>);
+impl RngCore for GameRng {
+    fn next_u32(&mut self) -> u32 {
+        self.0.borrow_mut().next_u32()
+    }
+    fn next_u64(&mut self) -> u64 {
+        self.0.borrow_mut().next_u64()
+    }
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.0.borrow_mut().fill_bytes(dest)
+    }
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.0.borrow_mut().try_fill_bytes(dest)
+    }
+}
 
+pub fn get_rng() -> GameRng {
+    RNG.with(|r| r.clone())
+}
+
+pub fn seed_rng(seed: u64) {
+    RNG.with(|r| *r.0.borrow_mut() = StdRng::seed_from_u64(seed));
+}
 
 pub fn is_hostile(entity: Entity, world: &World) -> bool {
     if let Some(actor) = world.get_component::<Actor>(entity) {
@@ -20,17 +56,47 @@ pub fn is_hostile(entity: Entity, world: &World) -> bool {
     false
 }
 
-pub fn visibility(world: &World, a: Vector2i, b: Vector2i) -> bool {
-    let line = get_line(a, b);
-    if line.len() <= 2 { return true }
-    if line.len() > VIEW_RANGE as usize { return false }
-    for v in line[1..line.len() - 1].iter() {
-        if get_entities_at_position(world, *v).iter()
-            .any(|&e| world.get_component::<ViewBlocker>(e).is_some()) {
-                return  false;
+pub fn field_of_view(world: &World, origin: Vector2i, range: u32) -> HashSet<Vector2i> {
+    let Some(board) = world.get_resource::<Board>() else { return HashSet::new() };
+    let blockers = world.query::<ViewBlocker>().with::<Position>().build().iter::<Position>()
+        .map(|p| p.0)
+        .collect::<HashSet<_>>();
+    visible_tiles(
+        origin,
+        &HashSet::from_iter(board.tiles.keys().map(|&v| v)),
+        &blockers,
+        range
+    )
+}
+
+pub fn get_noise_range(world: &World, source: Vector2i, volume: u32) -> HashSet<Vector2i> {
+    // flood fill - the sound does not pass through walls and closed doors
+    let Some(board) = world.get_resource::<Board>() else { return HashSet::new() };
+    let blockers = world.query::<Fixture>().with::<Obstacle>().with::<Position>().build()
+        .iter::<Position>()
+        .map(|p| p.0)
+        .collect::<HashSet<_>>();
+
+    let mut reached = HashSet::from_iter([source]);
+    let mut frontier = vec![source];
+    for _ in 0..volume {
+        let mut next = Vec::new();
+        for v in frontier {
+            for dir in ORTHO_DIRECTIONS {
+                let n = v + dir;
+                if !board.tiles.contains_key(&n) || blockers.contains(&n) { continue }
+                if reached.insert(n) { next.push(n) }
             }
+        }
+        frontier = next;
     }
-    true
+    reached
+}
+
+pub fn get_terrain(world: &World, v: Vector2i) -> Option<Terrain> {
+    let board = world.get_resource::<Board>()?;
+    let entity = *board.tiles.get(&v)?;
+    world.get_component::<Terrain>(entity).map(|t| t.clone())
 }
 
 pub fn get_entities_at_position(world: &World, v: Vector2i) -> Vec<Entity> {
@@ -53,10 +119,153 @@ pub fn spawn_with_position(
     let data = world.get_resource::<GameData>()?
         .entities.get(name).expect(&format!("Could not spawn: {} - no data found!", name)).clone();
     insert_data_components(entity, world, &data.components);
+    roll_blessing(entity, world);
+    roll_affixes(entity, world);
+    roll_loot(entity, world);
 
     Some(entity)
 }
 
+fn roll_loot(entity: Entity, world: &mut World) {
+    let level = world.get_resource::<Board>().map_or(1, |b| b.level);
+    let Some(data) = world.get_resource::<GameData>() else { return };
+    let Some(mut loot) = world.get_component_mut::<Loot>(entity) else { return };
+    let mut rng = get_rng();
+    let items = data.roll_loot(&loot.table, level, &mut rng);
+    loot.items = items;
+}
+
+fn roll_affixes(entity: Entity, world: &mut World) {
+    if world.get_component::<Weapon>(entity).is_none() { return }
+    let level = world.get_resource::<Board>().map_or(1, |b| b.level);
+    let Some(data) = world.get_resource::<GameData>() else { return };
+
+    let mut rng = get_rng();
+    let mut rolled = Vec::new();
+    for kind in [AffixKind::Prefix, AffixKind::Suffix] {
+        if !rng.gen_bool(AFFIX_CHANCE) { continue }
+        let pool = data.affixes.iter()
+            .filter(|(_, a)| a.kind == kind && a.min_level <= level)
+            .collect::<Vec<_>>();
+        // the affixes unlocked recently are favoured, up to twice their base weight
+        let weight = |a: &(&String, &AffixData)| {
+            a.1.spawn_chance.unwrap_or(1.) * (1. + a.1.min_level as f32 / level.max(1) as f32)
+        };
+        if let Ok((name, affix)) = pool.choose_weighted(&mut rng, weight) {
+            rolled.push((name.to_string(), (*affix).clone()));
+        }
+    }
+    drop(data);
+    if rolled.is_empty() { return }
+
+    let mut affixes = Affixes::default();
+    for (name, affix) in rolled {
+        apply_affix(entity, world, &affix);
+        match affix.kind {
+            AffixKind::Prefix => affixes.prefix = Some(name),
+            AffixKind::Suffix => affixes.suffix = Some(name)
+        }
+    }
+    let _ = world.insert_component(entity, affixes);
+}
+
+pub fn validate_affixes(data: &GameData) -> Result<(), String> {
+    // the attacks are kept as yaml in the data crate
+    for (name, affix) in data.affixes.iter() {
+        if affix.attacks.is_null() { continue }
+        if serde_yaml::from_value::<Vec<Attack>>(affix.attacks.clone()).is_err() {
+            return Err(format!("Invalid attacks in affix: {}", name));
+        }
+    }
+    Ok(())
+}
+
+fn apply_affix(entity: Entity, world: &mut World, affix: &AffixData) {
+    // checked by validate_affixes, values like 1-3 are rolled per spawn
+    if let Ok(attacks) = serde_yaml::from_value::<Vec<Attack>>(affix.attacks.clone()) {
+        if let Some(mut offensive) = world.get_component_mut::<Offensive>(entity) {
+            // attacks of the same kind are stacked
+            for attack in attacks {
+                match offensive.attacks.iter_mut().find(|a| a.kind == attack.kind) {
+                    Some(a) => a.value += attack.value,
+                    None => offensive.attacks.push(attack)
+                }
+            }
+        }
+    }
+    if let Some(mut durability) = world.get_component_mut::<Durability>(entity) {
+        durability.0 += affix.durability;
+    }
+    insert_data_components(entity, world, &affix.components);
+}
+
+fn roll_blessing(entity: Entity, world: &mut World) {
+    // only weapons and usable collectables can be blessed or cursed
+    let is_usable = world.get_component::<Collectable>(entity).is_some()
+        && world.get_component::<Effects>(entity).is_some();
+    let is_worn = world.get_component::<Weapon>(entity).is_some()
+        || world.get_component::<Equipment>(entity).is_some();
+    if !is_worn && !is_usable { return }
+
+    let mut rng = get_rng();
+    let kind = match rng.gen::<f64>() {
+        a if a < CURSE_CHANCE => BlessingKind::Cursed,
+        a if a < CURSE_CHANCE + BLESS_CHANCE => BlessingKind::Blessed,
+        _ => return
+    };
+    let _ = world.insert_component(entity, Blessing { kind, known: false });
+}
+
+pub fn get_armor(entity: Entity, world: &World) -> u32 {
+    get_worn_items(world, entity).into_iter()
+        .chain([entity])
+        .filter_map(|e| world.get_component::<Armor>(e).map(|a| a.0))
+        .sum()
+}
+
+pub fn is_resistant(entity: Entity, kind: AttackKind, world: &World) -> bool {
+    get_worn_items(world, entity).into_iter()
+        .chain([entity])
+        .filter_map(|e| world.get_component::<Resistance>(e))
+        .any(|r| r.0.contains(&kind))
+}
+
+pub fn get_stack_size(entity: Entity, world: &World) -> u32 {
+    world.get_component::<Stack>(entity).map_or(1, |s| s.0)
+}
+
+pub fn get_stack_target(entity: Entity, world: &World) -> Option<Entity> {
+    // inventory slot that the collectable can be stacked onto
+    let player = world.get_component::<Player>(get_player_entity(world)?)?;
+    let name = world.get_component::<Name>(entity)?;
+    let blessing = get_known_blessing(entity, world);
+    let values = get_effect_values(entity, world);
+    player.collectables.iter()
+        .find(|&&e| e != entity
+            && world.get_component::<Name>(e).map_or(false, |n| n.0 == name.0)
+            && get_known_blessing(e, world) == blessing
+            && get_effect_values(e, world) == values
+        )
+        .copied()
+}
+
+fn get_effect_values(entity: Entity, world: &World) -> Vec<u32> {
+    // rolled per spawn, so only equal rolls can share a stack
+    world.get_component::<Effects>(entity)
+        .map_or(Vec::new(), |e| e.effects.iter().map(|a| a.value).collect())
+}
+
+fn get_known_blessing(entity: Entity, world: &World) -> Option<BlessingKind> {
+    // a hidden blessing has to stack like a plain item, or it would give itself away
+    world.get_component::<Blessing>(entity).filter(|b| b.known).map(|b| b.kind)
+}
+
+pub fn is_cursed(entity: Entity, world: &World) -> bool {
+    world.get_component::<Blessing>(entity)
+        .map(|b| b.kind == BlessingKind::Cursed)
+        .unwrap_or(false)
+}
+
 pub fn deserialize_random_u32<'de, D>(d: D) -> Result<u32, D::Error>
 where D: Deserializer<'de> {
     if !d.is_human_readable() {
@@ -71,7 +280,7 @@ where D: Deserializer<'de> {
             let parts = s.split('-').collect::<Vec<_>>();
             if parts.len() != 2 { Err(serde::de::Error::custom("Wrong value!")) }
             else {
-                let mut rng = thread_rng();
+                let mut rng = get_rng();
                 let a = parts[0].parse::<u32>().map_err(serde::de::Error::custom)?;
                 let b = parts[1].parse::<u32>().map_err(serde::de::Error::custom)?;
                 Ok(rng.gen_range(a..=b))
@@ -0,0 +1,178 @@
+use rogalik::{
+    math::vectors::{Vector2i, ORTHO_DIRECTIONS},
+    storage::World
+};
+
+use hike_data::GameData;
+use hike_game::{
+    components::{Actor, Boss, Interactive, Item, Name, Obstacle, Openable, Position},
+    globals::LEVEL_COUNT,
+    structs::InteractionKind,
+    Board,
+    get_rng,
+    seed_rng,
+    get_entities_at_position,
+    validate_affixes
+};
+
+const FLOORS_PER_LEVEL: u64 = 100;
+const LAYOUTS: [&str; 6] = ["Bsp", "Arena", "Caves", "Maze", "Open", "Ring"];
+
+fn load_game_data() -> GameData {
+    let data = GameData::load_embedded();
+    validate_affixes(&data).expect("Invalid affix!");
+    data
+}
+
+fn generate_floor(data: &GameData, level: u32, seed: u64) -> World {
+    seed_rng(seed);
+    let mut world = World::new();
+    world.insert_resource(data.clone());
+    // items spawned during the generation read the level from the resource
+    world.insert_resource(Board::new(level, None));
+    let mut board = Board::new(level, None);
+    board.generate(&mut world);
+    world.insert_resource(board);
+    world
+}
+
+fn has_stair(world: &World) -> bool {
+    let query = world.query::<Interactive>().with::<Position>().build();
+    let result = query.iter::<Interactive>().any(|i| i.kind == InteractionKind::Ascend);
+    result
+}
+
+fn has_boss(world: &World) -> bool {
+    let result = world.query::<Boss>().with::<Position>().build().entities().next().is_some();
+    result
+}
+
+fn check_floor(world: &World, level: u32, boss: bool, seed: u64) {
+    let board = world.get_resource::<Board>().unwrap();
+    assert!(board.is_connected(world), "Disconnected floor at level {} (seed {})!", level, seed);
+    if boss {
+        assert!(has_boss(world), "Missing boss at level {} (seed {})!", level, seed);
+    } else if level < LEVEL_COUNT {
+        assert!(has_stair(world), "Missing stair at level {} (seed {})!", level, seed);
+    }
+}
+
+#[test]
+fn floors_are_connected() {
+    let data = load_game_data();
+    for level in 1..=LEVEL_COUNT {
+        let boss = data.levels.get(&level).is_some_and(|l| l.boss.is_some());
+        for i in 0..FLOORS_PER_LEVEL {
+            let seed = level as u64 * FLOORS_PER_LEVEL + i;
+            let world = generate_floor(&data, level, seed);
+            check_floor(&world, level, boss, seed);
+        }
+    }
+}
+
+#[test]
+fn layouts_are_connected() {
+    for (idx, layout) in LAYOUTS.iter().enumerate() {
+        let mut data = load_game_data();
+        data.add_level_data_from_str(format!("levels:\n  5:\n    layout: {}\n", layout));
+        for i in 0..FLOORS_PER_LEVEL {
+            let seed = idx as u64 * FLOORS_PER_LEVEL + i;
+            let world = generate_floor(&data, 5, seed);
+            check_floor(&world, 5, false, seed);
+        }
+    }
+}
+
+#[test]
+fn walled_off_item_is_disconnected() {
+    // spawn | wall | item
+    let mut world = World::new();
+    let mut board = Board::new(1, None);
+    for x in 0..3 {
+        let tile = world.spawn_entity();
+        board.tiles.insert(Vector2i::new(x, 0), tile);
+    }
+    let wall = world.spawn_entity();
+    let _ = world.insert_component(wall, Position(Vector2i::new(1, 0)));
+    let _ = world.insert_component(wall, Obstacle);
+    let item = world.spawn_entity();
+    let _ = world.insert_component(item, Position(Vector2i::new(2, 0)));
+    let _ = world.insert_component(item, Item);
+
+    assert!(!board.is_connected(&world));
+    world.despawn_entity(wall);
+    assert!(board.is_connected(&world));
+}
+
+fn get_named_positions(world: &World, name: &str) -> Vec<Vector2i> {
+    let query = world.query::<Name>().with::<Position>().build();
+    let result = query.iter::<Name>().zip(query.iter::<Position>())
+        .filter(|(n, _)| n.0 == name)
+        .map(|(_, p)| p.0)
+        .collect();
+    result
+}
+
+fn is_walled(world: &World, v: Vector2i) -> bool {
+    get_entities_at_position(world, v).iter()
+        .any(|&e| world.get_component::<Obstacle>(e).is_some()
+            && world.get_component::<Openable>(e).is_none()
+            && world.get_component::<Actor>(e).is_none()
+        )
+}
+
+#[test]
+fn toll_vault_is_closed() {
+    let mut data = load_game_data();
+    data.prefabs.retain(|name, _| name == "Toll_Vault");
+    let mut vaults = 0;
+    for seed in 0..FLOORS_PER_LEVEL {
+        let world = generate_floor(&data, 5, seed);
+        for door in get_named_positions(&world, "Barred_Door") {
+            // the vault's chest can only be reached through the door
+            let chests = get_named_positions(&world, "Chest");
+            let closed = ORTHO_DIRECTIONS.iter()
+                .map(|&d| door + d)
+                .filter(|v| chests.contains(v))
+                .any(|v| ORTHO_DIRECTIONS.iter()
+                    .map(|&d| v + d)
+                    .filter(|&n| n != door)
+                    .all(|n| is_walled(&world, n))
+                );
+            assert!(closed, "Open toll vault (seed {})!", seed);
+            vaults += 1;
+        }
+    }
+    assert!(vaults > 0, "No toll vault generated!");
+}
+
+fn get_floor_pieces(world: &World) -> Vec<(String, Vector2i)> {
+    let query = world.query::<Name>().with::<Position>().build();
+    let result = query.iter::<Name>().zip(query.iter::<Position>())
+        .map(|(n, p)| (n.0.clone(), p.0))
+        .collect();
+    result
+}
+
+#[test]
+fn seeded_floors_are_replayed() {
+    let data = load_game_data();
+    let get_appearances = |seed: u64| {
+        seed_rng(seed);
+        let mut data = data.clone();
+        data.assign_discoverables(&mut get_rng());
+        let mut appearances = data.appearances.iter()
+            .map(|(k, v)| (k.clone(), v.name.clone()))
+            .collect::<Vec<_>>();
+        appearances.sort();
+        appearances
+    };
+    assert!(get_appearances(1) == get_appearances(1), "Different appearances (seed 1)!");
+
+    for level in 1..=LEVEL_COUNT {
+        let seed = level as u64;
+        let a = get_floor_pieces(&generate_floor(&data, level, seed));
+        let b = get_floor_pieces(&generate_floor(&data, level, seed));
+        assert!(a == b, "Different floors at level {} (seed {})!", level, seed);
+    }
+}
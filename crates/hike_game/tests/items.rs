@@ -0,0 +1,66 @@
+use rogalik::{
+    math::vectors::Vector2i,
+    storage::{Entity, World}
+};
+
+use hike_game::{
+    actions::{Action, PickCollectable},
+    components::{Blessing, Collectable, Effects, Name, Player, Position},
+    structs::{BlessingKind, Effect, EffectKind},
+    get_stack_size
+};
+
+fn spawn_player(world: &mut World) -> Entity {
+    let entity = world.spawn_entity();
+    let _ = world.insert_component(entity, Player::default());
+    let _ = world.insert_component(entity, Position(Vector2i::ZERO));
+    entity
+}
+
+fn spawn_potion(world: &mut World, blessing: Option<BlessingKind>) -> Entity {
+    let entity = world.spawn_entity();
+    let _ = world.insert_component(entity, Name("Potion_of_Healing".to_string()));
+    let _ = world.insert_component(entity, Position(Vector2i::ZERO));
+    let _ = world.insert_component(entity, Collectable);
+    let _ = world.insert_component(entity, Effects {
+        effects: vec![Effect { kind: EffectKind::Heal, value: 3 }]
+    });
+    if let Some(kind) = blessing {
+        let _ = world.insert_component(entity, Blessing { kind, known: false });
+    }
+    entity
+}
+
+fn pick_two(blessing: Option<BlessingKind>) -> Vec<u32> {
+    // picks up a plain potion and then the tested one
+    let mut world = World::new();
+    let player = spawn_player(&mut world);
+    let plain = spawn_potion(&mut world, None);
+    let tested = spawn_potion(&mut world, blessing);
+    PickCollectable { entity: plain }.execute(&mut world).expect("Could not pick!");
+    PickCollectable { entity: tested }.execute(&mut world).expect("Could not pick!");
+
+    let collectables = world.get_component::<Player>(player).unwrap().collectables.clone();
+    collectables.iter().map(|&e| get_stack_size(e, &world)).collect()
+}
+
+#[test]
+fn hidden_blessings_stack_like_plain_items() {
+    let plain = pick_two(None);
+    assert_eq!(plain, vec![2]);
+    assert_eq!(pick_two(Some(BlessingKind::Cursed)), plain);
+    assert_eq!(pick_two(Some(BlessingKind::Blessed)), plain);
+}
+
+#[test]
+fn known_blessings_are_stacked_apart() {
+    let mut world = World::new();
+    let player = spawn_player(&mut world);
+    let plain = spawn_potion(&mut world, None);
+    let cursed = spawn_potion(&mut world, Some(BlessingKind::Cursed));
+    world.get_component_mut::<Blessing>(cursed).unwrap().known = true;
+    PickCollectable { entity: plain }.execute(&mut world).expect("Could not pick!");
+    PickCollectable { entity: cursed }.execute(&mut world).expect("Could not pick!");
+
+    assert_eq!(world.get_component::<Player>(player).unwrap().collectables.len(), 2);
+}
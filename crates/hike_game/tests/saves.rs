@@ -0,0 +1,139 @@
+use rogalik::{
+    math::vectors::Vector2i,
+    storage::World
+};
+
+use hike_data::GameData;
+use hike_game::{
+    components::{Health, Loot, Name, Position, Shop},
+    structs::{Offer, ValueMax},
+    Board, Floors, GameStats, register_serialized, restore, restore_floor, save, store_floor
+};
+
+fn spawn_stored(world: &mut World) {
+    let npc = world.spawn_entity();
+    let _ = world.insert_component(npc, Name("Rat".to_string()));
+    let _ = world.insert_component(npc, Position(Vector2i::new(1, 2)));
+    let _ = world.insert_component(npc, Health(ValueMax { current: 2, max: 5 }));
+
+    let chest = world.spawn_entity();
+    let _ = world.insert_component(chest, Name("Chest".to_string()));
+    let _ = world.insert_component(chest, Position(Vector2i::new(3, 4)));
+    let _ = world.insert_component(chest, Loot {
+        table: "Chest".to_string(),
+        items: vec!["Sword".to_string(), "Gold".to_string()],
+        chance: 1.
+    });
+
+    let merchant = world.spawn_entity();
+    let _ = world.insert_component(merchant, Name("Merchant".to_string()));
+    let _ = world.insert_component(merchant, Position(Vector2i::new(5, 6)));
+    let _ = world.insert_component(merchant, Shop {
+        size: 2,
+        stock: vec![Offer { name: "Axe".to_string(), price: 7 }]
+    });
+}
+
+fn leave_floor(world: &mut World) {
+    store_floor(world);
+    let to_remove = world.query::<Position>().build().entities()
+        .copied().collect::<Vec<_>>();
+    for entity in to_remove {
+        world.despawn_entity(entity);
+    }
+}
+
+fn check_restored(world: &World) {
+    let query = world.query::<Name>().with::<Position>().build();
+    let names = query.iter::<Name>().map(|n| n.0.clone()).collect::<Vec<_>>();
+    assert_eq!(names.len(), 3);
+    drop(query);
+
+    let query = world.query::<Health>().build();
+    let health = query.iter::<Health>().next().expect("Health was not restored!");
+    assert_eq!((health.0.current, health.0.max), (2, 5));
+    drop(query);
+
+    let query = world.query::<Loot>().build();
+    let loot = query.iter::<Loot>().next().expect("Loot was not restored!");
+    assert_eq!(loot.items, vec!["Sword".to_string(), "Gold".to_string()]);
+    drop(query);
+
+    let query = world.query::<Shop>().build();
+    let shop = query.iter::<Shop>().next().expect("Shop was not restored!");
+    assert_eq!(shop.size, 2);
+    assert_eq!(shop.stock.len(), 1);
+    assert_eq!((shop.stock[0].name.as_str(), shop.stock[0].price), ("Axe", 7));
+}
+
+#[test]
+fn floors_are_restored() {
+    let mut world = World::new();
+    world.insert_resource(Floors::default());
+    world.insert_resource(Board::new(1, None));
+    spawn_stored(&mut world);
+    leave_floor(&mut world);
+
+    world.insert_resource(Board::new(2, None));
+    assert_eq!(restore_floor(&mut world, 1, &None), Ok(true));
+    check_restored(&world);
+}
+
+#[test]
+fn floors_are_saved() {
+    let mut world = World::new();
+    register_serialized(&mut world);
+    world.insert_resource(GameStats::new());
+    world.insert_resource(Board::new(1, None));
+    spawn_stored(&mut world);
+    leave_floor(&mut world);
+    world.insert_resource(Board::new(2, Some("Armory".to_string())));
+    leave_floor(&mut world);
+    world.insert_resource(Board::new(3, None));
+    let saved_state = save(&world).expect("Could not save!");
+
+    let mut restored = World::new();
+    register_serialized(&mut restored);
+    restore(&mut restored, GameData::new(), saved_state).expect("Could not restore!");
+    {
+        let floors = restored.get_resource::<Floors>().unwrap();
+        assert_eq!(floors.0.len(), 2);
+        for (key, floor) in floors.0.iter() {
+            assert_eq!(floor.board.level, key.0);
+            assert_eq!(floor.board.branch, key.1);
+        }
+        assert!(floors.0.iter().any(|(k, _)| *k == (2, Some("Armory".to_string()))));
+    }
+
+    assert_eq!(restore_floor(&mut restored, 1, &None), Ok(true));
+    check_restored(&restored);
+}
+
+#[test]
+fn broken_floors_are_not_restored() {
+    let mut world = World::new();
+    world.insert_resource(Floors::default());
+    world.insert_resource(Board::new(1, None));
+    spawn_stored(&mut world);
+    leave_floor(&mut world);
+    if let Some(mut floors) = world.get_resource_mut::<Floors>() {
+        floors.0[0].1.entities[0].components = "Health: {current: rat}\n".to_string();
+    }
+
+    world.insert_resource(Board::new(2, None));
+    assert!(restore_floor(&mut world, 1, &None).is_err());
+    assert!(world.query::<Name>().build().entities().next().is_none());
+}
+
+#[test]
+fn outdated_saves_are_rejected() {
+    let mut world = World::new();
+    register_serialized(&mut world);
+    world.insert_resource(GameStats::new());
+    let mut saved_state = save(&world).expect("Could not save!");
+    saved_state[0] = saved_state[0].wrapping_add(1);
+
+    let mut restored = World::new();
+    register_serialized(&mut restored);
+    assert!(restore(&mut restored, GameData::new(), saved_state).is_err());
+}
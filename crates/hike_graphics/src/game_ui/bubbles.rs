@@ -6,13 +6,16 @@ use rogalik::storage::{Entity, World};
 use hike_game::{
     GameEvent,
     components::Position,
+    structs::BlessingKind,
+    get_player_entity
 };
 
 use super::super::{Context_, tile_to_world};
 use super::UiState;
+use super::utils::get_known_blessing;
 use crate::globals::{
     UI_BUBBLE_Z, UI_BUBBLE_MAX_AGE, UI_BUBBLE_SPEED, 
-    UI_OVERLAY_FONT_SIZE, HEALTH_COLOR, POISON_COLOR, IMMUNITY_COLOR
+    UI_OVERLAY_FONT_SIZE, GOLD_COLOR, HEALTH_COLOR, POISON_COLOR, IMMUNITY_COLOR
 };
 
 pub struct Bubble {
@@ -40,15 +43,44 @@ pub fn handle_game_event(
     match ev {
         GameEvent::Health(entity, value) => {
             bubble_value = Some((
-                entity,
+                *entity,
                 format!("{}{}", if *value < 0 { "" } else {"+"}, value),
                 HEALTH_COLOR
             ));
         },
+        GameEvent::Sacrifice(entity, value) => {
+            bubble_value = Some((*entity, format!("-{}max", value), HEALTH_COLOR));
+        },
+        GameEvent::Gamble(entity, is_win) => {
+            bubble_value = match is_win {
+                true => Some((*entity, "Lucky!".to_string(), GOLD_COLOR)),
+                false => Some((*entity, "Unlucky!".to_string(), POISON_COLOR))
+            };
+        },
+        GameEvent::Quaff(entity, true) => {
+            bubble_value = Some((*entity, "?!".to_string(), IMMUNITY_COLOR));
+        },
+        GameEvent::FindTrap(entity) => {
+            bubble_value = Some((*entity, "!".to_string(), GOLD_COLOR));
+        },
+        // item events are shown over the player
+        GameEvent::Enchant(_, value) => if let Some(player) = get_player_entity(world) {
+            bubble_value = Some((player, format!("+{}", value), GOLD_COLOR));
+        },
+        GameEvent::Identify(_) => if let Some(player) = get_player_entity(world) {
+            bubble_value = Some((player, "?!".to_string(), IMMUNITY_COLOR));
+        },
+        GameEvent::Reveal(entity) => if let Some(player) = get_player_entity(world) {
+            bubble_value = match get_known_blessing(*entity, world) {
+                Some(BlessingKind::Blessed) => Some((player, "Blessed!".to_string(), GOLD_COLOR)),
+                Some(BlessingKind::Cursed) => Some((player, "Cursed!".to_string(), POISON_COLOR)),
+                None => None
+            };
+        },
         _ => {}
     }
     if let Some(value) = bubble_value {
-        if let Some(position) = world.get_component::<Position>(*value.0) {
+        if let Some(position) = world.get_component::<Position>(value.0) {
             let mut rng = thread_rng();
             let offset = Vector2f::new(
                 rng.gen_range(0.0..0.5),
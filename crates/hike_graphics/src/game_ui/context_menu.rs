@@ -7,18 +7,20 @@ use core::sync::atomic::{AtomicUsize, AtomicBool, Ordering::Relaxed};
 
 use hike_data::Settings;
 use hike_game::{
-    actions::{Action, Interact, WieldWeapon, PickCollectable},
-    components::{Interactive, Collectable, Item, Name, Weapon},
+    actions::{Action, Equip, Interact, WieldWeapon, PickCollectable},
+    components::{Equipment, Interactive, Collectable, Item, Name, Player, Shop, Weapon},
+    globals::MAX_COLLECTABLES,
     get_player_position,
     get_player_entity,
     set_player_action,
-    get_entities_at_position
+    get_entities_at_position,
+    get_stack_target
 };
 
 use super::super::globals::{
     UI_BUTTON_HEIGHT, UI_GAP, UI_TEXT_GAP, UI_BUTTON_TEXT_SIZE, UI_BOTTOM_PANEL_HEIGHT, UI_STATUS_TEXT_SIZE
 };
-use super::{InputState, ButtonState, get_viewport_bounds};
+use super::{InputState, ButtonState, UiMode, UiState, get_viewport_bounds};
 use super::buttons::Button;
 use super::span::Span;
 use super::utils::{get_item_name, get_item_span, get_interactive_span};
@@ -30,29 +32,33 @@ pub fn handle_menu(
     world: &mut World,
     context: &mut crate::Context_,
     state: &InputState,
+    ui_state: &mut UiState,
     settings: &Settings
 ) -> bool {
     // true if clicked
     let Some(position) = get_player_position(world) else { return false };
 
-    let mut entities = get_entities_at_position(world, position)
+    let mut entries = get_entities_at_position(world, position)
         .iter()
         .filter(|&&e| world.get_component::<Interactive>(e).is_some() ||
+            world.get_component::<Shop>(e).is_some() ||
             world.get_component::<Weapon>(e).is_some() ||
+            world.get_component::<Equipment>(e).is_some() ||
             world.get_component::<Collectable>(e).is_some()
         )
-        .map(|&e| e)
+        .map(|&e| Some(e))
         .collect::<Vec<_>>();
+    entries.sort_by_key(|a| a.map(|e| (e.version, e.id)));
+    // the last entry opens the drop modal
+    if has_items(world) { entries.push(None) }
 
-    if entities.len() == 0 {
+    if entries.len() == 0 {
         CONTEXT_VISIBLE.store(false, Relaxed);
         return false;
     };
     
-    entities.sort_by_key(|a| (a.version, a.id));
-    
-    let cur_idx = CONTEXT_IDX.load(Relaxed);
-    CONTEXT_IDX.store(cur_idx % entities.len(), Relaxed);
+    let cur_idx = CONTEXT_IDX.load(Relaxed) % entries.len();
+    CONTEXT_IDX.store(cur_idx, Relaxed);
     CONTEXT_VISIBLE.store(true, Relaxed);
     
     let bounds = get_viewport_bounds(context);
@@ -62,13 +68,13 @@ pub fn handle_menu(
     } else {
         bounds.1.x - UI_BUTTON_HEIGHT - UI_GAP
     };
-    let width = match entities.len() {
+    let width = match entries.len() {
         0 => return false,
         1 => max_x - bounds.0.x - 2.0 * UI_GAP,
         _ => (max_x - bounds.0.x - 3.0 * UI_GAP) / 2.0
     };
 
-    if entities.len() > 1 {
+    if entries.len() > 1 {
         // draw `next` button
         let button = Button::new(
                 bounds.0.x + 2.0 * UI_GAP + width,
@@ -86,16 +92,44 @@ pub fn handle_menu(
     };
 
     
-    for (i, entity) in entities.iter().enumerate() {
+    for (i, entry) in entries.iter().enumerate() {
         if i != cur_idx { continue };
 
+        let Some(entity) = entry else {
+            let button = Button::new(
+                    bounds.0.x + UI_GAP,
+                    y,
+                    width,
+                    UI_BUTTON_HEIGHT
+                )
+                .with_sprite("ui", 0)
+                .with_span(Span::new().with_text_borrowed("DROP").with_size(UI_BUTTON_TEXT_SIZE));
+            button.draw(context);
+            if button.clicked(state) || state.action_left == ButtonState::Pressed {
+                ui_state.set_mode(UiMode::Drop);
+                return true;
+            }
+            continue
+        };
+
+        // no action -> open a modal
         let (text, action) = match entity {
+            e if world.get_component::<Shop>(*e).is_some() => ("TRADE", None),
             e if world.get_component::<Interactive>(*e).is_some() =>
-                ("USE", Box::new(Interact { entity: *e}) as Box<dyn Action>),
+                ("USE", Some(Box::new(Interact { entity: *e}) as Box<dyn Action>)),
+            e if world.get_component::<Collectable>(*e).is_some() && !can_pick(*e, world) =>
+                ("SWAP", None),
             e if world.get_component::<Collectable>(*e).is_some() =>
-                ("PICK", Box::new(PickCollectable { entity: *e }) as Box<dyn Action>),
-            e if world.get_component::<Weapon>(*e).is_some() =>
-                ("WIELD", Box::new(WieldWeapon { entity: *e }) as Box<dyn Action>),
+                ("PICK", Some(Box::new(PickCollectable { entity: *e }) as Box<dyn Action>)),
+            // full slots swap the held item with the one on the floor
+            e if world.get_component::<Weapon>(*e).is_some() => (
+                if has_free_weapon_slot(world) { "WIELD" } else { "SWAP" },
+                Some(Box::new(WieldWeapon { entity: *e }) as Box<dyn Action>)
+            ),
+            e if world.get_component::<Equipment>(*e).is_some() => (
+                if has_free_equipment_slot(*e, world) { "WEAR" } else { "SWAP" },
+                Some(Box::new(Equip { entity: *e }) as Box<dyn Action>)
+            ),
             _ => continue
         };
 
@@ -117,7 +151,16 @@ pub fn handle_menu(
             .with_span(span);
         button.draw(context);
         if button.clicked(state) || state.action_left == ButtonState::Pressed {
-            set_player_action(world, action);
+            match action {
+                Some(action) => { set_player_action(world, action); },
+                None if world.get_component::<Shop>(*entity).is_some() => {
+                    ui_state.set_mode(UiMode::Shop(*entity))
+                },
+                None => {
+                    ui_state.message = Some("Your inventory is full!".to_string());
+                    ui_state.set_mode(UiMode::Swap(*entity))
+                }
+            }
             return true;
         }
     }
@@ -154,3 +197,29 @@ fn draw_item_desc(world: &World, entity: Entity, context: &mut crate::Context_,
         }
     }
 }
+
+fn can_pick(entity: Entity, world: &World) -> bool {
+    let Some(player) = get_player_entity(world)
+        .and_then(|e| world.get_component::<Player>(e)) else { return false };
+    player.collectables.len() < MAX_COLLECTABLES || get_stack_target(entity, world).is_some()
+}
+
+fn has_items(world: &World) -> bool {
+    let Some(player) = get_player_entity(world)
+        .and_then(|e| world.get_component::<Player>(e)) else { return false };
+    player.weapons.iter().chain(player.equipment.iter()).any(|w| w.is_some())
+        || !player.collectables.is_empty()
+}
+
+fn has_free_weapon_slot(world: &World) -> bool {
+    let Some(player) = get_player_entity(world)
+        .and_then(|e| world.get_component::<Player>(e)) else { return false };
+    player.weapons.iter().any(|w| w.is_none())
+}
+
+fn has_free_equipment_slot(entity: Entity, world: &World) -> bool {
+    let Some(equipment) = world.get_component::<Equipment>(entity) else { return false };
+    let Some(player) = get_player_entity(world)
+        .and_then(|e| world.get_component::<Player>(e)) else { return false };
+    player.equipment[equipment.slot as usize].is_none()
+}
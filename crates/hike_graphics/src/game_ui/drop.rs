@@ -0,0 +1,50 @@
+use rogalik::storage::{Entity, World};
+
+use hike_game::{
+    actions::{Action, DropItem},
+    components::Player,
+    get_player_entity, set_player_action
+};
+
+use super::{InputState, UiMode, UiState};
+use super::modal::{ModalData, draw_modal};
+use super::utils::get_item_name;
+
+pub fn handle_drop(
+    world: &mut World,
+    context: &mut crate::Context_,
+    input_state: &InputState,
+    ui_state: &mut UiState
+) {
+    let Some(mut data) = get_modal_data(world) else {
+        ui_state.set_mode(UiMode::Game);
+        return
+    };
+    let Some(idx) = draw_modal(context, input_state, &data, &mut ui_state.modal_page) else { return };
+    if let Some(action) = data.choices.swap_remove(idx).1 {
+        set_player_action(world, action);
+    }
+    ui_state.set_mode(UiMode::Game);
+}
+
+fn get_modal_data(world: &World) -> Option<ModalData> {
+    let player = world.get_component::<Player>(get_player_entity(world)?)?;
+
+    let mut choices = player.weapons.iter()
+        .chain(player.equipment.iter())
+        .flatten()
+        .chain(player.collectables.iter())
+        .map(|&entity: &Entity| {
+            // cursed items fail to drop
+            let action = Box::new(DropItem { entity }) as Box<dyn Action>;
+            (get_item_name(entity, world).unwrap_or_default(), Some(action))
+        })
+        .collect::<Vec<_>>();
+    choices.push(("Cancel".to_string(), None));
+
+    let text = match choices.len() {
+        1 => "You have nothing to drop.".to_string(),
+        _ => "Drop which item?".to_string()
+    };
+    Some(ModalData { text, choices })
+}
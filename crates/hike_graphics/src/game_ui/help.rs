@@ -74,7 +74,7 @@ pub fn handle_help_menu(
         TAB_IDX.store(3, Relaxed);
     }
     if draw_menu_button(origin, button_width, 4, "Close", context, input_state) {
-        ui_state.mode = UiMode::Game;
+        ui_state.set_mode(UiMode::Game);
     }
 }
 
@@ -109,7 +109,7 @@ pub fn handle_help_button(
     ui_state: &mut UiState
 ) -> bool {
     if draw_help_button(context, input_state) {
-        ui_state.mode = UiMode::HelpMenu;
+        ui_state.set_mode(UiMode::HelpMenu);
         return true
     }
     false
@@ -140,7 +140,7 @@ fn draw_help_tab(
     state: &InputState
 ) {
     match TAB_IDX.load(Relaxed) {
-        0 => draw_symbols_tab(origin, context),
+        0 => draw_symbols_tab(origin, width, height, context),
         1 => draw_text_tab(ITEM_TEXT, origin, width, context),
         2 => draw_text_tab(WEAPON_TEXT, origin, width, context),
         3 => {
@@ -172,6 +172,8 @@ fn draw_build_version(origin: Vector2f, context: &mut crate::Context_, ver: &str
 
 fn draw_symbols_tab(
     origin: Vector2f,
+    width: f32,
+    height: f32,
     context: &mut crate::Context_
 ) {
     let data = [
@@ -183,16 +185,31 @@ fn draw_symbols_tab(
         (utils::ICON_LUNGE, "Lunge (hit 2 tiles in front)"),
         (utils::ICON_PUSH, "Push"),
         (utils::ICON_SWITCH, "Switch"),
+        (utils::ICON_ARMOR, "Armor (blocks hit damage)"),
+        (utils::ICON_RESISTANCE, "Resistance"),
+        (utils::ICON_LIGHT, "Light (radius)"),
         (utils::ICON_GOLD, "Gold"),
         (utils::ICON_HEAL, "Heal / Health"),
         (utils::ICON_IMMUNITY, "Immunity"),
         (utils::ICON_HEAL_POISON, "Heal Poison"),
         (utils::ICON_REGENERATION, "Regenerate"),
+        (utils::ICON_STEALTH, "Stealth (silent steps)"),
         (utils::ICON_TELEPORT, "Teleport"),
         (utils::ICON_LEVEL, "Level"),
+        (utils::ICON_IDENTIFY, "Identify"),
+        (utils::ICON_GAMBLE, "Gamble"),
+        (utils::ICON_SACRIFICE, "Sacrifice"),
+        (utils::ICON_OPEN, "Open"),
+        (utils::ICON_SLEEPING, "Sleeping"),
+        (utils::ICON_PATROL, "Patrolling"),
+        (utils::ICON_GUARD, "Guarding"),
     ];
     let mut v = origin;
     for d in data {
+        if origin.y - v.y + UI_BUTTON_TEXT_SIZE > height {
+            // wrap into the second column
+            v = Vector2f::new(origin.x + 0.5 * width, origin.y);
+        }
         let span = Span::new()
             .with_size(UI_BUTTON_TEXT_SIZE)
             .with_sprite("icons", d.0)
@@ -276,16 +293,17 @@ const CTRL_TEXT: &str =
 ---------------
   Swipe: move
   Swipe + hold: move continuously
-  Board tap: wait
+  Board tap: wait / search for traps
 
 KEYBOARD CONTROLS
 -----------------
   WASD / Arrows: move
-  Space: wait
+  Space: wait / search for traps
   Q: pick / interact
   E: [more] (if available)
   1234: change weapon slot
-  ZXCV: use item
+  ZXCVBN: use item
+  G: drop item
 ";
 
 const WEAPON_TEXT: &str =
@@ -294,7 +312,8 @@ const WEAPON_TEXT: &str =
 The player has 4 weapon slots available. Only one can be active at a time.
 Each weapon action (attack, pick, repair etc.) is always performed on the active slot.
 
-Beware: picking new weapon permanently replaces the active one.
+Picking a new weapon when all the slots are taken swaps it with the active one.
+The replaced weapon is left on the floor.
 
 Weapons have a durability parameter (marked by a hammer icon) that decreases with every use by one.
 
@@ -304,8 +323,12 @@ Weapon switching does NOT take a turn.
 const ITEM_TEXT: &str =
 "ITEMS
 -----
-The player can carry up to 4 items at a time. Newly picked item is always placed on the first free slot.
-When no slots are available, new items cannot be picked.
+The player can carry up to 6 items at a time. Newly picked item is always placed on the first free slot.
+Identical items are stacked on a single slot.
+When no slots are available, an item in the inventory can be swapped with the one on the floor.
+
+Any unwanted item (except cursed ones) can be dropped on the floor.
+Items left behind stay there - the stairs down lead back to the visited floors.
 
 Most of the items are randomized per each gameplay and have to be discovered on the first use.
 
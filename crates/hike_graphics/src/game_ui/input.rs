@@ -2,7 +2,8 @@ use rogalik::math::vectors::{Vector2i, Vector2f, ORTHO_DIRECTIONS};
 
 use hike_data::Settings;
 use hike_game::{
-    actions::Pause,
+    actions::Search,
+    get_player_entity,
     set_player_action,
     set_player_action_from_dir,
 };
@@ -52,8 +53,12 @@ pub fn handle_dir_input(
                 return
             }
         }
-        if buffer == InputDirection::Still && set_player_action(world, Box::new(Pause)) {
-            ui_state.direction_buffer = None;
+        if buffer == InputDirection::Still {
+            // waiting also searches the vicinity
+            let Some(entity) = get_player_entity(world) else { return };
+            if set_player_action(world, Box::new(Search { entity })) {
+                ui_state.direction_buffer = None;
+            }
         }
     }
 }
@@ -9,8 +9,9 @@ use hike_game::components::Player;
 use hike_game::globals::{MAX_COLLECTABLES, MAX_WEAPONS};
 use hike_game::set_player_action;
 
-use super::{InputState, ButtonState, get_viewport_bounds};
+use super::{InputState, ButtonState, UiMode, UiState, get_viewport_bounds};
 use super::buttons::Button;
+use super::span::Span;
 use super::super::globals::{
     UI_BUTTON_HEIGHT, UI_GAP, UI_BUTTON_TEXT_SIZE, BUTTON_COLOR,
     UI_BOTTOM_PANEL_HEIGHT, UI_BG_Z, UI_TEXT_Z, PIXEL
@@ -20,8 +21,10 @@ use super::utils::get_item_span;
 pub fn handle_inventory(
     world: &World,
     context: &mut crate::Context_,
-    state: &InputState
-) {
+    state: &InputState,
+    ui_state: &mut UiState
+) -> bool {
+    // true if the drop menu was opened
     let bounds = get_viewport_bounds(context);
 
     draw_inventory_panel(bounds.0, bounds.1.x - bounds.0.x, context);
@@ -42,11 +45,22 @@ pub fn handle_inventory(
         UI_BUTTON_TEXT_SIZE,
         Params2d { color: BUTTON_COLOR, ..Default::default() }
     );
+    let _ = context.graphics.draw_text(
+        "default",
+        "Armor / Shield / Ring",
+        bounds.0 + Vector2f::new(
+            PIXEL + UI_GAP,
+            3.5 * UI_GAP + 3. * UI_BUTTON_HEIGHT + 2. * UI_BUTTON_TEXT_SIZE
+        ),
+        UI_TEXT_Z,
+        UI_BUTTON_TEXT_SIZE,
+        Params2d { color: BUTTON_COLOR, ..Default::default() }
+    );
     
     let mut click = (None, None);
     {
         let query = world.query::<Player>().build();
-        let Some(player) = query.single::<Player>() else { return };
+        let Some(player) = query.single::<Player>() else { return false };
         click.0 = handle_inventory_buttons(
             bounds.0 + Vector2f::new(
                 0.,
@@ -71,6 +85,19 @@ pub fn handle_inventory(
             context,
             state
         );
+        // paper doll - worn items only show their stats for now
+        let _ = handle_inventory_buttons(
+            bounds.0 + Vector2f::new(
+                0.,
+                3. * UI_GAP + 2. * UI_BUTTON_HEIGHT + 2. * UI_BUTTON_TEXT_SIZE
+            ),
+            bounds.1.x - bounds.0.x,
+            &player.equipment.to_vec(),
+            None,
+            world,
+            context,
+            state
+        );
     }
 
     if state.digits[1] == ButtonState::Pressed { click.0 = Some(0) };
@@ -82,6 +109,8 @@ pub fn handle_inventory(
     if state.item_action[1] == ButtonState::Pressed { click.1 = Some(1) };
     if state.item_action[2] == ButtonState::Pressed { click.1 = Some(2) };
     if state.item_action[3] == ButtonState::Pressed { click.1 = Some(3) };
+    if state.item_action[4] == ButtonState::Pressed { click.1 = Some(4) };
+    if state.item_action[5] == ButtonState::Pressed { click.1 = Some(5) };
 
     if let Some(click) = click.0 {
         click_weapon(click, world);
@@ -89,6 +118,21 @@ pub fn handle_inventory(
     if let Some(click) = click.1 {
         click_item(click, world);
     }
+
+    let drop_button = Button::new(
+            bounds.1.x - UI_GAP - 2. * UI_BUTTON_HEIGHT,
+            bounds.0.y + 3. * UI_GAP + 3. * UI_BUTTON_HEIGHT + 2. * UI_BUTTON_TEXT_SIZE,
+            2. * UI_BUTTON_HEIGHT,
+            UI_BUTTON_TEXT_SIZE + 2. * UI_GAP
+        )
+        .with_sprite("ui", 0)
+        .with_span(Span::new().with_text_borrowed("DROP").with_size(UI_BUTTON_TEXT_SIZE));
+    drop_button.draw(context);
+    if drop_button.clicked(state) || state.drop == ButtonState::Pressed {
+        ui_state.set_mode(UiMode::Drop);
+        return true
+    }
+    false
 }
 
 fn handle_inventory_buttons(
@@ -1,10 +1,12 @@
 use rogalik::{
     math::vectors::Vector2f,
-    storage::World
+    storage::{Entity, World}
 };
 
+use hike_data::GameData;
 use hike_game::{
-    components::{Info, Stunned},
+    components::{Boss, Info, Interactive, Lockable, Name, Openable, Player, Stunned},
+    structs::{BlessingKind, InteractionKind},
     GameEvent,
     get_entities_at_position, get_player_position, get_player_entity
 };
@@ -12,6 +14,7 @@ use hike_game::{
 use super::super::globals::{UI_GAP, UI_TEXT_GAP, UI_STATUS_TEXT_SIZE};
 use super::get_viewport_bounds;
 use super::text_box::TextBox;
+use super::utils::{get_item_name, get_known_blessing};
 use super::UiState;
 
 pub fn update_messages(
@@ -97,10 +100,73 @@ pub fn handle_game_event(
                 text = Some("Slowly you regain your strength!");
             }
         },
+        GameEvent::Stealth(entity) => {
+            if Some(*entity) == get_player_entity(world) {
+                text = Some("Your steps become silent.");
+            }
+        },
         GameEvent::Travel(entity, _) => {
             if Some(*entity) == get_player_entity(world) {
                 state.message = None;
             }
+        },
+        GameEvent::Identify(entity) => {
+            let name = world.get_component::<Name>(*entity);
+            let data = world.get_resource::<GameData>();
+            let item_name = get_item_name(*entity, world);
+            if let (Some(name), Some(data), Some(item_name)) = (name, data, item_name) {
+                state.message = match data.appearances.get(&name.0) {
+                    Some(appearance) => Some(format!(
+                        "The {} is a {}!", appearance.name, item_name
+                    )),
+                    None => Some(format!("It is a {}!", item_name))
+                };
+            }
+        },
+        GameEvent::Reveal(entity) => {
+            let name = world.get_component::<Name>(*entity);
+            let blessing = get_known_blessing(*entity, world);
+            if let (Some(name), Some(blessing)) = (name, blessing) {
+                state.message = Some(format!(
+                    "The {} is {}!",
+                    name.0.replace("_", " "),
+                    match blessing {
+                        BlessingKind::Blessed => "blessed",
+                        BlessingKind::Cursed => "cursed, you can't let go of it"
+                    }
+                ));
+            }
+        },
+        GameEvent::Quaff(entity, true) => {
+            if let Some(name) = world.get_component::<Name>(*entity) {
+                state.message = Some(format!(
+                    "The {} desperately uses an item. Now you know what it does!",
+                    name.0.replace("_", " ")
+                ));
+            }
+        },
+        GameEvent::TriggerTrap(entity) => {
+            if Some(*entity) == get_player_entity(world) {
+                text = Some("You step on a pressure plate!");
+            }
+        },
+        GameEvent::FindTrap(_) => {
+            text = Some("You notice a hidden trap.");
+        },
+        GameEvent::Open(true) => {
+            text = Some("The lock clicks open.");
+        },
+        GameEvent::Refused(entity) => {
+            if let Some(refusal) = get_refusal_text(*entity, world) {
+                state.message = Some(refusal);
+            }
+        },
+        GameEvent::BossPhase(v) => {
+            if let Some(boss) = get_entities_at_position(world, *v).iter()
+                .find_map(|&e| world.get_component::<Boss>(e))
+            {
+                state.message = Some(format!("{} grows stronger!", boss.title));
+            }
         }
         _ => {}
     }
@@ -109,3 +175,21 @@ pub fn handle_game_event(
         state.message = Some(text.to_string());
     }
 }
+
+fn get_refusal_text(entity: Entity, world: &World) -> Option<String> {
+    // what the player is missing to open or use the entity
+    let player = world.get_component::<Player>(get_player_entity(world)?)?;
+    let interactive = world.get_component::<Interactive>(entity);
+    let keys = world.get_component::<Lockable>(entity).map_or(0, |l| l.keys)
+        + interactive.as_ref().map_or(0, |i| (i.kind == InteractionKind::Unlock) as u32);
+    if player.keys < keys {
+        return Some("You need a key.".to_string())
+    }
+    let cost = world.get_component::<Openable>(entity).and_then(|o| o.cost)
+        .or(interactive.and_then(|i| i.cost))
+        .unwrap_or(0);
+    if player.gold < cost {
+        return Some(format!("You need {} gold.", cost))
+    }
+    None
+}
@@ -13,6 +13,7 @@ use super::globals::{TILE_SIZE, BOARD_V_OFFSET, UI_TOP_OFFSET};
 pub mod bubbles;
 pub(crate) mod buttons;
 mod context_menu;
+mod drop;
 mod game_end;
 mod help;
 mod input;
@@ -20,8 +21,10 @@ mod inventory;
 mod messages;
 mod modal;
 mod overlays;
+mod shop;
 pub(crate) mod span;
 mod status;
+mod swap;
 mod text_box;
 mod utils;
 
@@ -36,13 +39,16 @@ pub struct InputState {
     pub action_right: ButtonState,
     // pub pause: ButtonState,
     pub digits: [ButtonState; 10],
-    pub item_action: [ButtonState; 4], // ZXCV
+    pub item_action: [ButtonState; 6], // ZXCVBN
+    pub drop: ButtonState,
 }
 
 pub struct UiState {
     // dir, is_world_input
     pub direction_buffer: Option<(InputDirection, bool)>,
     mode: UiMode,
+    // of the open modal, reset with the mode
+    modal_page: usize,
     bubbles: Vec<bubbles::Bubble>,
     game_duration: f32,
     pub build_version: String,
@@ -55,6 +61,7 @@ impl UiState {
         Self {
             direction_buffer: None,
             mode: UiMode::default(),
+            modal_page: 0,
             bubbles: Vec::new(),
             game_duration: 0.,
             build_version: String::new(),
@@ -63,6 +70,10 @@ impl UiState {
             last_action: None
         }
     }
+    fn set_mode(&mut self, mode: UiMode) {
+        self.mode = mode;
+        self.modal_page = 0;
+    }
 }
 
 #[derive(Default)]
@@ -71,6 +82,9 @@ pub enum UiMode {
     Game,
     HelpMenu,
     GameEnd,
+    Shop(Entity),
+    Swap(Entity),
+    Drop
 }
 
 #[derive(Clone, Copy, Default, Debug, PartialEq)]
@@ -126,13 +140,25 @@ pub fn ui_update(
             update_game_ui(world, input_state, ui_state, context, settings);
             if let Some(stats) = world.get_resource::<hike_game::GameStats>() {
                 if get_player_entity(world).is_none() || stats.win {
-                    ui_state.mode = UiMode::GameEnd;
+                    ui_state.set_mode(UiMode::GameEnd);
                     ui_state.game_duration = stats.start.elapsed()
                 }
             }
         },
         UiMode::HelpMenu => help::handle_help_menu(context, input_state, ui_state, settings),
-        UiMode::GameEnd => game_end::handle_menu(context, input_state, ui_state, events, world)
+        UiMode::GameEnd => game_end::handle_menu(context, input_state, ui_state, events, world),
+        UiMode::Shop(entity) => {
+            status::draw_status(world, context);
+            shop::handle_shop(world, context, input_state, ui_state, entity)
+        },
+        UiMode::Swap(entity) => {
+            status::draw_status(world, context);
+            swap::handle_swap(world, context, input_state, ui_state, entity)
+        },
+        UiMode::Drop => {
+            status::draw_status(world, context);
+            drop::handle_drop(world, context, input_state, ui_state)
+        }
     }
 }
 
@@ -145,13 +171,16 @@ fn update_game_ui(
 ) {
     handle_action_events(world, ui_state);
     status::draw_status(world, context);
+    status::draw_boss_health(world, context);
     let mut ui_click = false;
 
-    inventory::handle_inventory(world, context, input_state);
+    if inventory::handle_inventory(world, context, input_state, ui_state) {
+        ui_click = true
+    }
     messages::update_messages(world, context, ui_state);
     bubbles::handle_bubbles(world, ui_state, context);
 
-    if context_menu::handle_menu(world, context, input_state, settings) {
+    if context_menu::handle_menu(world, context, input_state, ui_state, settings) {
         ui_click = true
     }
     if help::handle_help_button(context, input_state, ui_state) {
@@ -1,39 +1,97 @@
-use rogalik::engine::{Color, GraphicsContext};
+use rogalik::{
+    engine::{GraphicsContext, Params2d},
+    math::vectors::Vector2f
+};
 use hike_game::actions::Action;
 
-use super::InputState;
+use super::super::globals::{
+    UI_BG_Z, UI_BUTTON_HEIGHT, UI_BUTTON_TEXT_SIZE, UI_GAP, UI_MODAL_PAGE_SIZE, UI_STATUS_TEXT_SIZE
+};
+use super::{ButtonState, InputState, get_viewport_bounds};
+use super::buttons::Button;
+use super::span::Span;
+use super::text_box::TextBox;
 
+// the digits 9 and 0 are taken by the paging and closing rows
+const _: () = assert!(UI_MODAL_PAGE_SIZE <= 8);
+
+// the last choice closes the modal
 pub struct ModalData {
     pub text: String,
     pub choices: Vec<(String, Option<Box<dyn Action>>)>
 }
 
 pub fn draw_modal(
-    context: &crate::Context_,
+    context: &mut crate::Context_,
     input_state: &InputState,
-    data: &ModalData
+    data: &ModalData,
+    page: &mut usize
 ) -> Option<usize> {
-    let viewport_size = context.get_physical_size();
-    let mut clicked = None;
+    // returns the index of the selected choice
+    let last = data.choices.len().checked_sub(1)?;
+    let page_count = ((last + UI_MODAL_PAGE_SIZE - 1) / UI_MODAL_PAGE_SIZE).max(1);
+    *page %= page_count;
+    let start = *page * UI_MODAL_PAGE_SIZE;
+
+    // keys from 1 up to the page size pick from the page, 9 turns it and 0 closes
+    let mut rows = (start..last.min(start + UI_MODAL_PAGE_SIZE))
+        .enumerate()
+        .map(|(i, idx)| (i + 1, Some(idx), data.choices[idx].0.as_str()))
+        .collect::<Vec<_>>();
+    if page_count > 1 { rows.push((9, None, "[MORE]")) }
+    rows.push((0, Some(last), data.choices[last].0.as_str()));
 
-    // for (i, entry) in data.choices.iter().enumerate() {
-    //     if Button::new(
-    //             viewport_size.x / 2. - 200.,
-    //             viewport_size.y / 2. - 50. + i as f32 * 50.,
-    //             400.,
-    //             40.,
-    //         )
-    //         .with_color(SpriteColor(0, 0, 0, 0))
-    //         .with_text(
-    //             &entry.0,
-    //             SpriteColor(255, 255, 255, 255),
-    //             24
-    //         )
-    //         .draw(context)
-    //         .clicked(input_state) {
-    //             clicked = Some(i)
-    //         }
-    // }
+    let bounds = get_viewport_bounds(context);
+    let width = bounds.1.x - bounds.0.x - 2. * UI_GAP;
+    let text_height = 3. * UI_STATUS_TEXT_SIZE;
+    let height = text_height + 3. * UI_GAP
+        + rows.len() as f32 * (UI_BUTTON_HEIGHT + UI_GAP);
+    let origin = Vector2f::new(
+        bounds.0.x + UI_GAP,
+        0.5 * (bounds.0.y + bounds.1.y - height)
+    );
 
+    let _ = context.graphics.draw_atlas_sprite(
+        "ui",
+        0,
+        origin,
+        UI_BG_Z,
+        Vector2f::new(width, height),
+        Params2d { slice: Some((4, Vector2f::new(1., 1.))), ..Default::default() }
+    );
+
+    TextBox::new()
+        .with_text_borrowed(&data.text)
+        .with_size(UI_STATUS_TEXT_SIZE)
+        .draw(
+            origin + Vector2f::new(2. * UI_GAP, height - 2. * UI_GAP),
+            width - 4. * UI_GAP,
+            context
+        );
+
+    let mut clicked = None;
+    let mut y = origin.y + height - text_height - 2. * UI_GAP;
+    for (key, idx, text) in rows {
+        y -= UI_BUTTON_HEIGHT + UI_GAP;
+        let button = Button::new(
+                origin.x + UI_GAP,
+                y,
+                width - 2. * UI_GAP,
+                UI_BUTTON_HEIGHT
+            )
+            .with_sprite("ui", 0)
+            .with_span(
+                Span::new()
+                    .with_text_owned(format!("{}. {}", key, text))
+                    .with_size(UI_BUTTON_TEXT_SIZE)
+            );
+        button.draw(context);
+        if button.clicked(input_state) || input_state.digits[key] == ButtonState::Pressed {
+            match idx {
+                Some(idx) => clicked = Some(idx),
+                None => *page += 1
+            }
+        }
+    }
     clicked
 }
@@ -6,7 +6,8 @@ use rogalik::{
 
 use hike_game::{
     Board,
-    components::{Health, Player, Poisoned},
+    components::{Actor, Health, Player, Poisoned},
+    structs::Behaviour
 };
 
 use crate::GraphicsState;
@@ -14,6 +15,8 @@ use crate::globals::{TILE_SIZE, UI_OVERLAY_Z, UI_OVERLAY_FONT_SIZE, HEALTH_COLOR
 use crate::world_to_tile;
 use crate::graphics::renderers::get_entity_sprite;
 
+use super::utils::{ICON_GUARD, ICON_PATROL, ICON_SLEEPING};
+
 pub fn draw_overlays(
     world: &World,
     context: &mut crate::Context_,
@@ -57,4 +60,36 @@ pub fn draw_overlays(
             Params2d { color, ..Default::default() }
         );
     }
+    draw_behaviours(world, context, state);
+}
+
+fn draw_behaviours(
+    world: &World,
+    context: &mut crate::Context_,
+    state: &GraphicsState
+) {
+    let query = world.query::<Actor>().build();
+    let Some(board) = world.get_resource::<Board>() else { return };
+
+    for (actor, &entity) in query.iter::<Actor>().zip(query.entities()) {
+        let icon = match actor.behaviour {
+            Behaviour::Sleeping => ICON_SLEEPING,
+            Behaviour::Patrol => ICON_PATROL,
+            Behaviour::Guard => ICON_GUARD,
+            _ => continue
+        };
+
+        let Some(base) = get_entity_sprite(entity, state) else { continue };
+        let tile = world_to_tile(base.v);
+        if !board.visible.contains(&tile) { continue; }
+
+        let _ = context.graphics.draw_atlas_sprite(
+            "icons",
+            icon as usize,
+            base.v + Vector2f::new(0., TILE_SIZE - UI_OVERLAY_FONT_SIZE),
+            UI_OVERLAY_Z,
+            Vector2f::new(UI_OVERLAY_FONT_SIZE, UI_OVERLAY_FONT_SIZE),
+            Params2d::default()
+        );
+    }
 }
\ No newline at end of file
@@ -0,0 +1,57 @@
+use rogalik::storage::{Entity, World};
+
+use hike_game::{
+    actions::{Action, Buy},
+    components::{Player, Position, Shop},
+    get_player_entity, get_player_position, set_player_action
+};
+
+use super::{InputState, UiMode, UiState};
+use super::modal::{ModalData, draw_modal};
+use super::utils::get_offer_name;
+
+pub fn handle_shop(
+    world: &mut World,
+    context: &mut crate::Context_,
+    input_state: &InputState,
+    ui_state: &mut UiState,
+    entity: Entity
+) {
+    // close the shop when the player is not standing at it anymore
+    let shop_v = world.get_component::<Position>(entity).map(|p| p.0);
+    if shop_v.is_none() || get_player_position(world) != shop_v {
+        ui_state.set_mode(UiMode::Game);
+        return
+    }
+    let Some(mut data) = get_modal_data(world, entity) else {
+        ui_state.set_mode(UiMode::Game);
+        return
+    };
+    let Some(idx) = draw_modal(context, input_state, &data, &mut ui_state.modal_page) else { return };
+    match data.choices.swap_remove(idx).1 {
+        Some(action) => { set_player_action(world, action); },
+        None => ui_state.set_mode(UiMode::Game)
+    }
+}
+
+fn get_modal_data(world: &World, entity: Entity) -> Option<ModalData> {
+    let shop = world.get_component::<Shop>(entity)?;
+    let gold = world.get_component::<Player>(get_player_entity(world)?)?.gold;
+
+    let mut choices = shop.stock.iter()
+        .enumerate()
+        .map(|(index, offer)| {
+            let name = get_offer_name(&offer.name, world).unwrap_or(offer.name.to_string());
+            // the purchase fails if the player cannot afford it
+            let action = Box::new(Buy { entity, index }) as Box<dyn Action>;
+            (format!("{} ({} gold)", name, offer.price), Some(action))
+        })
+        .collect::<Vec<_>>();
+    choices.push(("Leave".to_string(), None));
+
+    let text = match shop.stock.len() {
+        0 => "Sold out! Come back another time.".to_string(),
+        _ => format!("Take a look at my wares. You have {} gold.", gold)
+    };
+    Some(ModalData { text, choices })
+}
@@ -6,11 +6,13 @@ use rogalik::{
 
 use hike_game::{
     Board,
-    components::{Health, Player, Poisoned, Immune, Regeneration, Stunned},
+    components::{Boss, Health, Player, Poisoned, Position, Immune, Regeneration, Stealthy, Stunned},
     get_entities_at_position, get_player_position
 };
 
-use super::super::globals::{UI_GAP, UI_TEXT_GAP, UI_STATUS_TEXT_SIZE};
+use super::super::globals::{
+    UI_BG_Z, UI_GAP, UI_TEXT_GAP, UI_STATUS_TEXT_SIZE, BACKGROUND_COLOR, HEALTH_COLOR, PIXEL
+};
 use super::get_viewport_bounds;
 use super::span::Span;
 use crate::game_ui::utils;
@@ -24,11 +26,16 @@ pub fn draw_status(world: &World, context: &mut crate::Context_) {
 
     let spacer = 0.5 * UI_GAP;
 
+    let level = match &board.branch {
+        Some(branch) => format!("{} {}", board.level, branch.replace("_", " ")),
+        None => format!("{}", board.level)
+    };
+
     let mut span = Span::new()
         .with_size(UI_STATUS_TEXT_SIZE)
         .with_text_color(Color(150, 128, 128, 255))
         .with_sprite("icons", utils::ICON_LEVEL)
-        .with_text_owned(format!("{}", board.level))
+        .with_text_owned(level)
         .with_spacer(spacer)
         .with_sprite("icons", utils::ICON_HEAL)
         .with_text_owned(format!("{}/{}", health.0.current, health.0.max))
@@ -36,6 +43,12 @@ pub fn draw_status(world: &World, context: &mut crate::Context_) {
         .with_sprite("icons", utils::ICON_GOLD)
         .with_text_owned(format!("{}", player.gold));
 
+    if player.keys > 0 {
+        span = span.with_spacer(spacer)
+            .with_sprite("icons", utils::ICON_KEY)
+            .with_text_owned(format!("{}", player.keys));
+    }
+
     if let Some(poisoned) = world.get_component::<Poisoned>(query.single_entity().unwrap()) {
         span = span.with_spacer(spacer)
             .with_sprite("icons", utils::ICON_POISON)
@@ -52,6 +65,11 @@ pub fn draw_status(world: &World, context: &mut crate::Context_) {
             .with_sprite("icons", utils::ICON_REGENERATION)
             .with_text_owned(format!("{}", regeneration.0));
     }
+    if let Some(stealthy) = world.get_component::<Stealthy>(query.single_entity().unwrap()) {
+        span = span.with_spacer(spacer)
+            .with_sprite("icons", utils::ICON_STEALTH)
+            .with_text_owned(format!("{}", stealthy.0));
+    }
     if let Some(stunned) = world.get_component::<Stunned>(query.single_entity().unwrap()) {
         span = span.with_spacer(spacer)
             .with_sprite("icons", utils::ICON_STUN)
@@ -66,3 +84,48 @@ pub fn draw_status(world: &World, context: &mut crate::Context_) {
 
     span.draw(v, context);
 }
+
+pub fn draw_boss_health(world: &World, context: &mut crate::Context_) {
+    let Some(board) = world.get_resource::<Board>() else { return };
+    let query = world.query::<Boss>().with::<Health>().with::<Position>().build();
+
+    // show the bar only when the boss is in sight
+    let Some((boss, health)) = query.iter::<Boss>()
+        .zip(query.iter::<Health>())
+        .zip(query.iter::<Position>())
+        .find(|(_, p)| board.visible.contains(&p.0))
+        .map(|(a, _)| a)
+        else { return };
+
+    let bounds = get_viewport_bounds(context);
+    let top = bounds.1.y - UI_GAP - UI_STATUS_TEXT_SIZE - UI_TEXT_GAP;
+    let x = bounds.0.x + UI_GAP;
+
+    let span = Span::new()
+        .with_size(UI_STATUS_TEXT_SIZE)
+        .with_text_color(HEALTH_COLOR)
+        .with_text_owned(boss.title.to_string());
+    span.draw(Vector2f::new(x, top), context);
+
+    let offset = span.width(context) + UI_GAP;
+    let width = bounds.1.x - x - offset - UI_GAP;
+    let origin = Vector2f::new(x + offset, top - UI_STATUS_TEXT_SIZE);
+    let fill = health.0.current as f32 / health.0.max.max(1) as f32;
+
+    let _ = context.graphics.draw_atlas_sprite(
+        "ui",
+        utils::UI_BAR_SPRITE,
+        origin,
+        UI_BG_Z,
+        Vector2f::new(width, UI_STATUS_TEXT_SIZE),
+        Params2d { color: BACKGROUND_COLOR, ..Default::default() }
+    );
+    let _ = context.graphics.draw_atlas_sprite(
+        "ui",
+        utils::UI_BAR_SPRITE,
+        origin + Vector2f::new(PIXEL, PIXEL),
+        UI_BG_Z + 1,
+        Vector2f::new(fill * (width - 2. * PIXEL), UI_STATUS_TEXT_SIZE - 2. * PIXEL),
+        Params2d { color: HEALTH_COLOR, ..Default::default() }
+    );
+}
@@ -0,0 +1,52 @@
+use rogalik::storage::{Entity, World};
+
+use hike_game::{
+    actions::{Action, SwapCollectable},
+    components::{Player, Position},
+    get_player_entity, get_player_position, set_player_action
+};
+
+use super::{InputState, UiMode, UiState};
+use super::modal::{ModalData, draw_modal};
+use super::utils::get_item_name;
+
+pub fn handle_swap(
+    world: &mut World,
+    context: &mut crate::Context_,
+    input_state: &InputState,
+    ui_state: &mut UiState,
+    entity: Entity
+) {
+    // close when the item is not lying under the player anymore
+    let item_v = world.get_component::<Position>(entity).map(|p| p.0);
+    if item_v.is_none() || get_player_position(world) != item_v {
+        ui_state.set_mode(UiMode::Game);
+        return
+    }
+    let Some(mut data) = get_modal_data(world, entity) else {
+        ui_state.set_mode(UiMode::Game);
+        return
+    };
+    let Some(idx) = draw_modal(context, input_state, &data, &mut ui_state.modal_page) else { return };
+    if let Some(action) = data.choices.swap_remove(idx).1 {
+        set_player_action(world, action);
+    }
+    ui_state.set_mode(UiMode::Game);
+}
+
+fn get_modal_data(world: &World, entity: Entity) -> Option<ModalData> {
+    let player = world.get_component::<Player>(get_player_entity(world)?)?;
+    let name = get_item_name(entity, world)?;
+
+    let mut choices = player.collectables.iter()
+        .enumerate()
+        .map(|(index, &e)| {
+            let action = Box::new(SwapCollectable { entity, index }) as Box<dyn Action>;
+            (format!("Drop {}", get_item_name(e, world).unwrap_or_default()), Some(action))
+        })
+        .collect::<Vec<_>>();
+    choices.push(("Leave".to_string(), None));
+
+    let text = format!("Your inventory is full. Swap for the {}?", name);
+    Some(ModalData { text, choices })
+}
@@ -3,14 +3,14 @@ use rogalik::{
     storage::{Entity, World}
 };
 
-use hike_data::GameData;
+use hike_data::{Appearance, GameData};
 use hike_game::{
     components::{
-        Durability, Discoverable, Effects, Lunge, Name, Interactive,
-        Offensive, Player, Push, Swing, Switch
+        Affixes, Armor, Blessing, Defensive, Durability, Discoverable, Effects, Light, Lunge, Mending,
+        Name, Interactive, Offensive, Player, Push, Resistance, Swing, Switch
     },
-    structs::{Attack, AttackKind, Effect, EffectKind, InteractionKind},
-    get_player_entity
+    structs::{Attack, AttackKind, BlessingKind, Effect, EffectKind, InteractionKind},
+    get_player_entity, get_stack_size
 };
 use crate::game_ui::span::Span;
 
@@ -30,35 +30,86 @@ pub const ICON_IMMUNITY: u32 = 18;
 pub const ICON_HEAL_POISON: u32 = 19;
 pub const ICON_TELEPORT: u32 = 20;
 pub const ICON_REGENERATION: u32 = 21;
+pub const ICON_STEALTH: u32 = 22;
 
 pub const ICON_LEVEL: u32 = 24;
 pub const ICON_WIN: u32 = 25;
+pub const ICON_SLEEPING: u32 = 26;
+pub const ICON_PATROL: u32 = 27;
+pub const ICON_GUARD: u32 = 28;
+pub const ICON_IDENTIFY: u32 = 29;
+pub const ICON_GAMBLE: u32 = 30;
+pub const ICON_SACRIFICE: u32 = 31;
+pub const ICON_ARMOR: u32 = 32;
+pub const ICON_RESISTANCE: u32 = 33;
+pub const ICON_KEY: u32 = 34;
+pub const ICON_LIGHT: u32 = 35;
+pub const ICON_OPEN: u32 = 36;
 
+// plain white tile in the ui atlas, to be tinted
+pub const UI_BAR_SPRITE: usize = 4;
 
-fn needs_discovery(entity: Entity, world: &World) -> Option<(&str, Color)> {
+
+fn needs_discovery(entity: Entity, world: &World) -> Option<Appearance> {
     if world.get_component::<Discoverable>(entity).is_none() { return None };
     let name = world.get_component::<Name>(entity)?.0.clone();
     let player_entity = get_player_entity(world)?;
     let player = world.get_component::<Player>(player_entity)?;
     if player.discovered.contains(&name) { return None };
     let data = world.get_resource::<GameData>()?;
-    data.discoverable_colors.get(&name).map(|&a| a)
+    data.appearances.get(&name).cloned()
 }
 
 
 pub fn get_item_name(entity: Entity, world: &World) -> Option<String> {
-    if let Some(color) = needs_discovery(entity, world) {
-        return Some(format!("Unidentified {} item", color.0));
+    if let Some(appearance) = needs_discovery(entity, world) {
+        return Some(appearance.name);
+    }
+    let mut name = world.get_component::<Name>(entity)?.0.clone();
+    if let Some(affixes) = world.get_component::<Affixes>(entity) {
+        if let Some(prefix) = &affixes.prefix {
+            name = format!("{}_{}", prefix, name);
+        }
+        if let Some(suffix) = &affixes.suffix {
+            name = format!("{}_{}", name, suffix);
+        }
+    }
+    let name = name.replace("_", " ");
+    match get_known_blessing(entity, world) {
+        Some(BlessingKind::Blessed) => Some(format!("Blessed {}", name)),
+        Some(BlessingKind::Cursed) => Some(format!("Cursed {}", name)),
+        None => Some(name)
+    }
+}
+
+pub fn get_known_blessing(entity: Entity, world: &World) -> Option<BlessingKind> {
+    let blessing = world.get_component::<Blessing>(entity)?;
+    if !blessing.known { return None }
+    Some(blessing.kind)
+}
+
+
+pub fn get_offer_name(name: &str, world: &World) -> Option<String> {
+    // name of a not yet spawned (eg. sold) item
+    let player = world.get_component::<Player>(get_player_entity(world)?)?;
+    let data = world.get_resource::<GameData>()?;
+    if !player.discovered.contains(name) {
+        if let Some(appearance) = data.appearances.get(name) {
+            return Some(appearance.name.clone());
+        }
     }
-    Some(world.get_component::<Name>(entity)?.0.clone().replace("_", " "))
+    Some(name.replace("_", " "))
 }
 
 
 pub fn get_item_span<'a>(entity: Entity, world: &World) -> Span<'a> {
-    if let Some(color) = needs_discovery(entity, world) {
-        return Span::new()
-            .with_text_color(color.1)
-            .with_text_borrowed("?")
+    let count = get_stack_size(entity, world);
+    if let Some(appearance) = needs_discovery(entity, world) {
+        let span = Span::new()
+            .with_text_color(appearance.color)
+            .with_text_borrowed("?");
+        if count > 1 { return span.with_text_owned(format!(" x{}", count)) }
+        return span
     }
 
     let mut span = Span::new()
@@ -72,6 +123,9 @@ pub fn get_item_span<'a>(entity: Entity, world: &World) -> Span<'a> {
             span = span.with_text_owned(format!("{}", val));
         }
     }
+    if count > 1 {
+        span = span.with_text_owned(format!(" x{}", count));
+    }
     span
 }
 
@@ -99,9 +153,10 @@ fn get_entity_icons(entity: Entity, world: &World) -> Vec<(u32, Option<u32>)> {
     let mut output = Vec::new();
 
     if let Some(effects) = world.get_component::<Effects>(entity) {
+        let blessing = get_known_blessing(entity, world);
         output.extend(
             effects.effects.iter()
-                .map(|e| get_effect_icon(e))
+                .map(|e| get_effect_icon(&e.with_blessing(blessing)))
         );
     }
     if let Some(offensive) = world.get_component::<Offensive>(entity) {
@@ -110,6 +165,27 @@ fn get_entity_icons(entity: Entity, world: &World) -> Vec<(u32, Option<u32>)> {
                 .map(|e| get_attack_icon(e))
         );
     }
+    if let Some(armor) = world.get_component::<Armor>(entity) {
+        output.push((ICON_ARMOR, Some(armor.0)));
+    }
+    if let Some(defensive) = world.get_component::<Defensive>(entity) {
+        output.extend(
+            defensive.attacks.iter()
+                .map(|e| get_attack_icon(e))
+        );
+    }
+    if let Some(resistance) = world.get_component::<Resistance>(entity) {
+        for kind in resistance.0.iter() {
+            output.push((ICON_RESISTANCE, None));
+            output.push((get_attack_kind_icon(kind), None));
+        }
+    }
+    if let Some(light) = world.get_component::<Light>(entity) {
+        output.push((ICON_LIGHT, Some(light.0)));
+    }
+    if let Some(mending) = world.get_component::<Mending>(entity) {
+        output.push((ICON_REGENERATION, Some(mending.cooldown.max)));
+    }
     if let Some(durability) = world.get_component::<Durability>(entity) {
         output.push((ICON_DURABILITY, Some(durability.0)));
     }
@@ -129,12 +205,15 @@ fn get_entity_icons(entity: Entity, world: &World) -> Vec<(u32, Option<u32>)> {
 }
 
 fn get_attack_icon(attack: &Attack) -> (u32, Option<u32>) {
-    let icon = match attack.kind {
+    (get_attack_kind_icon(&attack.kind), Some(attack.value))
+}
+
+fn get_attack_kind_icon(kind: &AttackKind) -> u32 {
+    match kind {
         AttackKind::Hit => ICON_HIT,
         AttackKind::Poison => ICON_POISON,
         AttackKind::Stun => ICON_STUN,
-    };
-    (icon, Some(attack.value))
+    }
 }
 
 fn get_effect_icon(effect: &Effect) -> (u32, Option<u32>) {
@@ -143,8 +222,10 @@ fn get_effect_icon(effect: &Effect) -> (u32, Option<u32>) {
         EffectKind::Heal => ICON_HEAL,
         EffectKind::HealPoison => ICON_HEAL_POISON,
         EffectKind::Immunity => ICON_IMMUNITY,
+        EffectKind::Key => ICON_KEY,
         EffectKind::Poison => ICON_POISON,
         EffectKind::Regenerate => ICON_REGENERATION,
+        EffectKind::Stealth => ICON_STEALTH,
         EffectKind::Teleport => ICON_TELEPORT,
         EffectKind::Win => ICON_WIN
     };
@@ -154,13 +235,27 @@ fn get_effect_icon(effect: &Effect) -> (u32, Option<u32>) {
 fn get_interaction_icon(interaction: &InteractionKind) -> (u32, Option<String>) {
     match interaction {
         InteractionKind::Ascend => (ICON_LEVEL, None),
+        InteractionKind::Branch(b) => (ICON_LEVEL, Some(b.replace("_", " "))),
+        InteractionKind::Descend => (ICON_LEVEL, Some("-1".to_string())),
+        InteractionKind::Gamble(_) => (ICON_GAMBLE, None),
+        InteractionKind::Identify => (ICON_IDENTIFY, None),
+        InteractionKind::Open => (ICON_OPEN, None),
         InteractionKind::Repair(v) => (
             ICON_DURABILITY,
             Some(format!("+{}", v))
         ),
+        InteractionKind::Sacrifice(v) => (
+            ICON_SACRIFICE,
+            Some(format!("-{}max", v))
+        ),
         InteractionKind::UpgradeHealth(v) => (
             ICON_HEAL,
             Some(format!("+{}max", v))
-        )
+        ),
+        InteractionKind::UpgradeOffensive(v) => (
+            ICON_HIT,
+            Some(format!("+{}", v))
+        ),
+        InteractionKind::Unlock => (ICON_KEY, Some("-1".to_string()))
     }
 }
\ No newline at end of file
@@ -8,6 +8,7 @@ pub const BACKGROUND_COLOR: super::Color = super::Color(38, 18, 34, 255);
 pub const HEALTH_COLOR: super::Color = super::Color(255, 126, 102, 255);
 pub const IMMUNITY_COLOR: super::Color = super::Color(189, 200, 220, 255);
 pub const POISON_COLOR: super::Color = super::Color(145, 200, 185, 255);
+pub const GOLD_COLOR: super::Color = super::Color(255, 191, 102, 255);
 
 pub const ACTOR_Z: i32 = 200;
 pub const FIXTURE_Z: i32 = 100;
@@ -27,14 +28,20 @@ pub const UI_BUBBLE_SPEED: f32 = 1.;
 pub const UI_OVERLAY_FONT_SIZE: f32 = 0.4;
 
 pub const INACTIVE_FADE: f32 = 0.5;
+// fog over the unlit visible tiles on dark floors
+pub const DARK_FOG_ALPHA: u8 = 128;
+// fog reduction per light level
+pub const LIGHT_FOG_STEP: u8 = 40;
 pub const ANIMATION_TICK: f32 = 1.;
 
 pub const MOVEMENT_SPEED: f32 = 10.;
 pub const FADE_SPEED: f32 = 5.;
 
 // ui
-pub const UI_BOTTOM_PANEL_HEIGHT: f32 = 4.25;
+pub const UI_BOTTOM_PANEL_HEIGHT: f32 = 6.25;
 pub const UI_BUTTON_HEIGHT: f32 = 1.25;
+// modal choices shown at once, apart from the paging and closing ones
+pub const UI_MODAL_PAGE_SIZE: usize = 6;
 pub const UI_GAP: f32 = 3. * PIXEL;
 pub const UI_TEXT_GAP: f32 = 2. * PIXEL;
 pub const UI_BUTTON_TEXT_SIZE: f32 = 8. * PIXEL;
@@ -10,7 +10,7 @@ use hike_data::GameData;
 use hike_game::{
     GameEvent,
     Board,
-    components::{Actor, Discoverable, Fixture, Item, Name, Stunned, Player, Position, Projectile, Tile},
+    components::{Actor, Discoverable, Fixture, Hidden, Item, Name, Stunned, Player, Position, Projectile, Tile},
     globals::BOARD_SIZE,
     get_entities_at_position, get_player_entity
 };
@@ -21,7 +21,8 @@ use super::super::{
 use super::utils::move_towards;
 use crate::globals::{
     TILE_SIZE, ACTOR_Z, FIXTURE_Z, ITEM_Z, PROJECTILE_Z, TILE_Z, FOG_Z,
-    MOVEMENT_SPEED, INACTIVE_FADE, FADE_SPEED, BACKGROUND_COLOR
+    MOVEMENT_SPEED, INACTIVE_FADE, FADE_SPEED, BACKGROUND_COLOR, DARK_FOG_ALPHA,
+    LIGHT_FOG_STEP
 };
 
 #[derive(Debug, PartialEq)]
@@ -249,6 +250,7 @@ pub fn draw_sprites(world: &World, state: &GraphicsState, context: &mut Context_
     for sprite in state.sprites.iter() {
         let tile = world_to_tile(sprite.v);
         if !board.discovered.contains(&tile) { continue; }
+        if world.get_component::<Hidden>(sprite.entity).is_some() { continue; }
         if !board.visible.contains(&tile) {
             if world.get_component::<Actor>(sprite.entity).is_some() { continue; }
         }
@@ -275,16 +277,31 @@ pub fn draw_fog(world: &World, state: &GraphicsState, context: &mut Context_) {
     for x in -2..BOARD_SIZE as i32 + 2 {
         for y in -2..BOARD_SIZE as i32 + 2 {
             let vi = Vector2i::new(x, y);
-            if board.visible.contains(&vi) { continue; }
-            let idx = if board.discovered.contains(&vi) { 1 } else { 0 };
-
+            let (idx, alpha) = if board.visible.contains(&vi) {
+                // on dark floors the visible tiles are dimmed unless lit
+                if !board.dark { continue; }
+                let light = board.light.get(&vi).copied().unwrap_or(0).min(u8::MAX as u32) as u8;
+                (1, DARK_FOG_ALPHA.saturating_sub(light.saturating_mul(LIGHT_FOG_STEP)))
+            } else if board.discovered.contains(&vi) {
+                (1, BACKGROUND_COLOR.3)
+            } else {
+                (0, BACKGROUND_COLOR.3)
+            };
+            if alpha == 0 { continue; }
+
+            let color = Color(
+                BACKGROUND_COLOR.0,
+                BACKGROUND_COLOR.1,
+                BACKGROUND_COLOR.2,
+                alpha
+            );
             let _ = context.graphics.draw_atlas_sprite(
                 "fog",
                 idx,
                 tile_to_world(vi) - Vector2f::new(0.5, 0.5) * TILE_SIZE,
                 FOG_Z,
                 Vector2f::new(TILE_SIZE, TILE_SIZE) * 2.0,
-                Params2d { color: BACKGROUND_COLOR, ..Default::default() }
+                Params2d { color, ..Default::default() }
             );
         }
     }
@@ -319,10 +336,15 @@ fn get_sprite_renderer(
         &format!("No data found for {}", name.0)
     );
 
+    // branches can recolor the entities
+    let palette = world.get_resource::<Board>()
+        .and_then(|b| b.branch.clone())
+        .and_then(|b| game_data.branches.get(&b)?.palette.get(&name.0).copied());
+
     let color = match world.get_component::<Discoverable>(entity) {
-        Some(_) => game_data.discoverable_colors.get(&name.0)
-            .expect(&format!("No color assigned for {}!", name.0)).1,
-        None => data.sprite.color
+        Some(_) => game_data.appearances.get(&name.0)
+            .expect(&format!("No appearance assigned for {}!", name.0)).color,
+        None => palette.unwrap_or(data.sprite.color)
     };
 
     let frame_count = match data.sprite.frames {